@@ -0,0 +1,387 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::gpu_rtx;
+use crate::image::{Image, ImageFormat};
+use crate::rtx::{
+    sample_scene, scene_file, stereo, Camera, CancellationToken, CheckpointError,
+    CheckpointOptions, RenderControl, Scene, SceneFileError, StderrProgress, Stereo, StereoLayout,
+    TileOptions, TileOrder,
+};
+
+const USAGE: &str = "\
+Usage: rtrace [OPTIONS]
+
+Options:
+  -b, --backend <st|mt|gpu>     Rendering backend [default: mt]
+  -s, --scene <FILE>            Scene file to render [default: built-in sample]
+  -W, --width <PIXELS>          Image width
+  -H, --height <PIXELS>         Image height
+  -n, --spp <COUNT>             Samples per pixel
+  -d, --max-depth <COUNT>       Maximum ray bounces
+      --seed <SEED>             Random seed
+      --spectral                Trace wavelengths so dispersive glass splits light
+      --stereo <LAYOUT>         Render both eyes, side-by-side or top-bottom
+  -j, --threads <COUNT>         Worker threads for mt [default: available cores]
+      --tile-size <PIXELS>      Tile edge length for mt [default: 16]
+      --tile-order <ORDER>      scanline, spiral or hilbert [default: scanline]
+      --checkpoint <FILE>       Render progressively (st or mt), saving and resuming here
+  -o, --output <FILE>           Output image, `-` for stdout [default: output.ppm]
+  -f, --format <ppm|pfm>        Output format [default: from extension, else ppm]
+  -h, --help                    Print this help";
+
+const GPU_BATCH_SIZE: usize = 10; // rays generated per pixel per GPU pass
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    SingleThread,
+    MultiThread,
+    Gpu,
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "st" => Some(Backend::SingleThread),
+            "mt" => Some(Backend::MultiThread),
+            "gpu" => Some(Backend::Gpu),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Usage(String),
+    Scene(SceneFileError),
+    Checkpoint(CheckpointError),
+    Lowering(gpu_rtx::LoweringError),
+    Gpu(gpu_rtx::GpuError),
+    Io(io::Error),
+    Cancelled,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) => write!(f, "{}\n\n{}", msg, USAGE),
+            CliError::Scene(err) => write!(f, "{}", err),
+            CliError::Checkpoint(err) => write!(f, "{}", err),
+            CliError::Lowering(err) => write!(f, "{}", err),
+            CliError::Gpu(err) => write!(f, "{}", err),
+            CliError::Io(err) => write!(f, "failed to write image: {}", err),
+            CliError::Cancelled => write!(f, "Render cancelled."),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<SceneFileError> for CliError {
+    fn from(err: SceneFileError) -> Self {
+        CliError::Scene(err)
+    }
+}
+
+impl From<CheckpointError> for CliError {
+    fn from(err: CheckpointError) -> Self {
+        CliError::Checkpoint(err)
+    }
+}
+
+impl From<gpu_rtx::LoweringError> for CliError {
+    fn from(err: gpu_rtx::LoweringError) -> Self {
+        CliError::Lowering(err)
+    }
+}
+
+impl From<gpu_rtx::GpuError> for CliError {
+    fn from(err: gpu_rtx::GpuError) -> Self {
+        CliError::Gpu(err)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(err: io::Error) -> Self {
+        CliError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub backend: Backend,
+    pub scene: Option<PathBuf>,
+    pub width: Option<usize>,
+    pub height: Option<usize>,
+    pub samples_per_pixel: Option<usize>,
+    pub max_depth: Option<usize>,
+    pub seed: Option<u64>,
+    pub spectral: bool,
+    pub stereo: Option<StereoLayout>,
+    pub tiles: TileOptions,
+    pub checkpoint: Option<PathBuf>,
+    pub output: PathBuf,
+    pub format: Option<ImageFormat>,
+    pub help: bool,
+}
+
+impl Options {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            scene: None,
+            width: None,
+            height: None,
+            samples_per_pixel: None,
+            max_depth: None,
+            seed: None,
+            spectral: false,
+            stereo: None,
+            tiles: TileOptions::default(),
+            checkpoint: None,
+            output: PathBuf::from("output.ppm"),
+            format: None,
+            help: false,
+        }
+    }
+
+    /// Parses command line arguments (without the program name), starting
+    /// from `backend` when no `--backend` is given.
+    pub fn parse(
+        args: impl IntoIterator<Item = String>,
+        backend: Backend,
+    ) -> Result<Self, CliError> {
+        let mut options = Self::new(backend);
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value for {}", name)))
+            };
+
+            match arg.as_str() {
+                "-b" | "--backend" => {
+                    let name = value(&arg)?;
+                    options.backend = Backend::from_name(&name)
+                        .ok_or_else(|| CliError::Usage(format!("unknown backend '{}'", name)))?;
+                }
+                "-s" | "--scene" => options.scene = Some(PathBuf::from(value(&arg)?)),
+                "-W" | "--width" => options.width = Some(count(&arg, &value(&arg)?)?),
+                "-H" | "--height" => options.height = Some(count(&arg, &value(&arg)?)?),
+                "-n" | "--spp" => options.samples_per_pixel = Some(count(&arg, &value(&arg)?)?),
+                "-d" | "--max-depth" => options.max_depth = Some(number(&arg, &value(&arg)?)?),
+                "--seed" => {
+                    let seed = number(&arg, &value(&arg)?)?;
+                    options.seed = Some(seed);
+                    options.tiles.seed = Some(seed);
+                }
+                "--spectral" => options.spectral = true,
+                "--stereo" => {
+                    let name = value(&arg)?;
+                    options.stereo = Some(StereoLayout::parse(&name).ok_or_else(|| {
+                        CliError::Usage(format!("unknown stereo layout '{}'", name))
+                    })?);
+                }
+                "-j" | "--threads" => options.tiles.num_threads = count(&arg, &value(&arg)?)?,
+                "--tile-size" => options.tiles.tile_size = count(&arg, &value(&arg)?)?,
+                "--tile-order" => {
+                    let name = value(&arg)?;
+                    options.tiles.order = match name.as_str() {
+                        "scanline" => TileOrder::Scanline,
+                        "spiral" => TileOrder::Spiral,
+                        "hilbert" => TileOrder::Hilbert,
+                        _ => return Err(CliError::Usage(format!("unknown tile order '{}'", name))),
+                    };
+                }
+                "--checkpoint" => options.checkpoint = Some(PathBuf::from(value(&arg)?)),
+                "-o" | "--output" => options.output = PathBuf::from(value(&arg)?),
+                "-f" | "--format" => {
+                    let name = value(&arg)?;
+                    options.format =
+                        Some(ImageFormat::from_name(&name).ok_or_else(|| {
+                            CliError::Usage(format!("unknown format '{}'", name))
+                        })?);
+                }
+                "-h" | "--help" => options.help = true,
+                _ => return Err(CliError::Usage(format!("unexpected argument '{}'", arg))),
+            }
+        }
+
+        if options.backend == Backend::Gpu && options.checkpoint.is_some() {
+            return Err(CliError::Usage(
+                "--checkpoint is not supported by the gpu backend".to_string(),
+            ));
+        }
+        Ok(options)
+    }
+}
+
+fn number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Usage(format!("invalid value '{}' for {}", value, name)))
+}
+
+// A number of pixels, samples or threads, which must be at least one.
+fn count(name: &str, value: &str) -> Result<usize, CliError> {
+    match number(name, value)? {
+        0 => Err(CliError::Usage(format!("{} must be at least 1", name))),
+        count => Ok(count),
+    }
+}
+
+/// Entry point shared by all binaries: parses the process arguments,
+/// renders and writes the image, exiting with a message on failure.
+pub fn main(default_backend: Backend) {
+    let result = Options::parse(std::env::args().skip(1), default_backend).and_then(|options| {
+        if options.help {
+            println!("{}", USAGE);
+            Ok(())
+        } else {
+            run(&options)
+        }
+    });
+
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        let code = match err {
+            CliError::Cancelled => 130,
+            _ => 1,
+        };
+        std::process::exit(code);
+    }
+}
+
+pub fn run(options: &Options) -> Result<(), CliError> {
+    if let Some(seed) = options.seed {
+        fastrand::seed(seed);
+    }
+
+    let image = match options.backend {
+        Backend::SingleThread | Backend::MultiThread => render_cpu(options)?,
+        Backend::Gpu => render_gpu(options)?,
+    };
+
+    write_image(&image, &options.output, options.format)
+}
+
+// The scene file (or built-in sample) with command line overrides applied.
+// Both CPU and GPU backends render this same scene.
+fn load_scene(options: &Options) -> Result<Scene, CliError> {
+    let scene = match &options.scene {
+        Some(path) => scene_file::load(path)?,
+        None => sample_scene::get(),
+    };
+    Ok(Scene {
+        camera: apply_overrides(&scene.camera, options),
+        ..scene
+    })
+}
+
+fn render_cpu(options: &Options) -> Result<Image, CliError> {
+    let scene = load_scene(options)?;
+    let control = RenderControl::new(Arc::new(StderrProgress), cancel_on_ctrl_c());
+
+    let output = match (options.backend, &options.checkpoint) {
+        (_, Some(path)) => {
+            let mut checkpoint = CheckpointOptions::new(path);
+            checkpoint.seed = options.seed.unwrap_or(checkpoint.seed);
+            // st renders progressively too, with a single worker.
+            let tiles = match options.backend {
+                Backend::SingleThread => TileOptions {
+                    num_threads: 1,
+                    ..options.tiles
+                },
+                _ => options.tiles,
+            };
+            scene.render_progressive(&tiles, &checkpoint, &control)?
+        }
+        (Backend::SingleThread, None) => scene.render_st(&control),
+        (_, None) => scene.render_mt(&options.tiles, &control),
+    };
+
+    if output.cancelled {
+        return Err(CliError::Cancelled);
+    }
+    let camera = scene.camera;
+    Ok(Image::new(
+        camera.image_width,
+        camera.image_height,
+        output.image,
+    ))
+}
+
+fn render_gpu(options: &Options) -> Result<Image, CliError> {
+    let scene = gpu_rtx::lower(&load_scene(options)?)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let pixels = runtime.block_on(gpu_rtx::render(
+        &scene.camera,
+        &scene.spheres,
+        scene.samples_per_pixel,
+        GPU_BATCH_SIZE,
+    ))?;
+    Ok(Image::from_gpu(
+        scene.camera.image_width,
+        scene.camera.image_height,
+        &pixels,
+    ))
+}
+
+// Rebuilds the camera with any resolution, sample or depth overrides.
+fn apply_overrides(camera: &Camera, options: &Options) -> Camera {
+    let mut overridden = Camera::new(
+        options.width.unwrap_or(camera.image_width),
+        options.height.unwrap_or(camera.image_height),
+        options
+            .samples_per_pixel
+            .unwrap_or(camera.samples_per_pixel),
+        options.max_depth.unwrap_or(camera.max_depth),
+        camera.vfov,
+        camera.lookfrom,
+        camera.lookat,
+        camera.vup,
+        camera.defocus_angle,
+        camera.focus_dist,
+    );
+    overridden.spectral = camera.spectral || options.spectral;
+    overridden.projection = camera.projection;
+    overridden.lens = camera.lens.clone();
+    overridden.aperture = camera.aperture.clone();
+    overridden.stereo = match (camera.stereo, options.stereo) {
+        (Some(stereo), Some(layout)) => Some(Stereo { layout, ..stereo }),
+        (None, Some(layout)) => Some(Stereo::new(layout, stereo::INTEROCULAR, camera.focus_dist)),
+        (stereo, None) => stereo,
+    };
+    overridden
+}
+
+fn write_image(image: &Image, path: &Path, format: Option<ImageFormat>) -> Result<(), CliError> {
+    if path == Path::new("-") {
+        let mut out = io::stdout().lock();
+        image.write(&mut out, format.unwrap_or(ImageFormat::Ppm))?;
+        out.flush()?;
+    } else {
+        image.save_as(path, format)?;
+        eprintln!("Written to {}", path.display());
+    }
+    Ok(())
+}
+
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to build signal runtime");
+        if runtime.block_on(tokio::signal::ctrl_c()).is_ok() {
+            token.cancel();
+        }
+    });
+    cancel
+}
@@ -1,4 +1,5 @@
 use super::{Ray, Vector};
+use crate::rtx::Aperture;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RenderTask {
@@ -12,7 +13,7 @@ impl RenderTask {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
     pub aspect_ratio: f32,     // Ratio of image width to height
     pub image_width: usize,    // Rendered image width in pixels
@@ -24,6 +25,7 @@ pub struct Camera {
     pub vup: Vector<3>,        // camera relative up direction
     pub defocus_angle: f32,    // variation angle of rays through each pixel
     pub focus_dist: f32,       // distance from camera lookfrom point to plane of perfect focus
+    pub aperture: Aperture,    // shape of the defocus disk
     center: Vector<3>,         // Camera center
     pixel00_loc: Vector<3>,    // Location of pixel (0, 0)
     pixel_delta_u: Vector<3>,  // Horizontal delta to the next pixel
@@ -33,6 +35,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         image_width: usize,
         image_height: usize,
@@ -53,8 +56,8 @@ impl Camera {
         let viewport_width = viewport_height * aspect_ratio;
 
         let w = (lookfrom - lookat).normalize();
-        let u = (-1.0 * vup.cross(w)).normalize();
-        let v = (-1.0 * w).cross(u).normalize();
+        let u = vup.cross(w).normalize();
+        let v = w.cross(u);
 
         let viewport_u = viewport_width * u;
         let viewport_v = viewport_height * (-1.0 * v);
@@ -86,14 +89,13 @@ impl Camera {
             defocus_disk_u,
             defocus_disk_v,
             focus_dist,
+            aperture: Aperture::Circle,
         }
     }
 
     fn defocus_disk_sample(&self) -> Vector<3> {
-        let p = Vector::<3>::random_in_unit_disk();
-        self.center
-            + (p.components[0] * self.defocus_disk_u)
-            + (p.components[1] * self.defocus_disk_v)
+        let (x, y) = self.aperture.sample();
+        self.center + (x as f32 * self.defocus_disk_u) + (y as f32 * self.defocus_disk_v)
     }
 
     pub fn generate_rays_count(&self, count: usize) -> Vec<Ray> {
@@ -35,7 +35,7 @@ impl Interval {
         if x > self.max {
             return self.max;
         }
-        return x;
+        x
     }
 
     pub const EMPTY: Interval = Interval {
@@ -0,0 +1,176 @@
+use std::fmt;
+
+use super::{Camera, Sphere, Vector};
+use crate::rtx;
+
+// Lowers the CPU scene model to what the compute shader understands: a
+// flat buffer of `Sphere`s and an f32 camera. Anything the shader cannot
+// represent is reported instead of being silently approximated.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoweringError {
+    UnsupportedMaterial(&'static str),
+    UnsupportedTarget(&'static str),
+    UnsupportedFeature(&'static str),
+}
+
+impl fmt::Display for LoweringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoweringError::UnsupportedMaterial(name) => {
+                write!(f, "the gpu backend does not support {} materials", name)
+            }
+            LoweringError::UnsupportedTarget(name) => {
+                write!(f, "the gpu backend does not support {} objects", name)
+            }
+            LoweringError::UnsupportedFeature(name) => {
+                write!(f, "the gpu backend does not support {}", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoweringError {}
+
+#[derive(Debug, Clone)]
+pub struct GpuScene {
+    pub camera: Camera,
+    pub spheres: Vec<Sphere>,
+    pub samples_per_pixel: usize,
+}
+
+pub fn lower(scene: &rtx::Scene) -> Result<GpuScene, LoweringError> {
+    if scene.camera.spectral {
+        return Err(LoweringError::UnsupportedFeature("spectral rendering"));
+    }
+    if scene.camera.projection != rtx::Projection::Perspective {
+        return Err(LoweringError::UnsupportedFeature(
+            "orthographic or panoramic projections",
+        ));
+    }
+    if scene.camera.lens.is_some() {
+        return Err(LoweringError::UnsupportedFeature("lens prescriptions"));
+    }
+    if scene.camera.stereo.is_some() {
+        return Err(LoweringError::UnsupportedFeature("stereo rendering"));
+    }
+    if !scene.lighting.lights.is_empty() {
+        return Err(LoweringError::UnsupportedFeature(
+            "point, spot or directional lights",
+        ));
+    }
+    if scene.lighting.background != rtx::Background::Gradient {
+        return Err(LoweringError::UnsupportedFeature(
+            "environment maps or physical skies",
+        ));
+    }
+
+    let targets: &[rtx::Target] = match scene.world.as_ref() {
+        rtx::Hittable::Single(target) => std::slice::from_ref(target),
+        rtx::Hittable::Multiple(list) => &list.list,
+        rtx::Hittable::MultiplePtr(list) => &list.list,
+    };
+
+    let spheres = targets
+        .iter()
+        .map(lower_target)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(GpuScene {
+        camera: lower_camera(&scene.camera),
+        spheres,
+        samples_per_pixel: scene.camera.samples_per_pixel,
+    })
+}
+
+fn lower_camera(camera: &rtx::Camera) -> Camera {
+    let mut lowered = Camera::new(
+        camera.image_width,
+        camera.image_height,
+        camera.max_depth,
+        camera.vfov as f32,
+        vector(camera.lookfrom),
+        vector(camera.lookat),
+        vector(camera.vup),
+        camera.defocus_angle as f32,
+        camera.focus_dist as f32,
+    );
+    lowered.aperture = camera.aperture.clone();
+    lowered
+}
+
+fn lower_target(target: &rtx::Target) -> Result<Sphere, LoweringError> {
+    match target {
+        rtx::Target::Sphere(sphere) => lower_sphere(sphere),
+    }
+}
+
+fn lower_sphere(sphere: &rtx::SphereData) -> Result<Sphere, LoweringError> {
+    let mut lowered = Sphere {
+        center: vector(sphere.center).components,
+        radius: sphere.radius as f32,
+        albedo: [1.0, 1.0, 1.0],
+        material_id: Sphere::LAMBERTIAN,
+        fuzz: 0.0,
+        refraction_index: 0.0,
+        _pad1: 0.0,
+        _pad2: 0.0,
+    };
+
+    match &sphere.material {
+        rtx::Material::Lambertian(data) => {
+            lowered.albedo = vector(data.albedo).components;
+        }
+        rtx::Material::Metal(data) => {
+            lowered.material_id = Sphere::METAL;
+            lowered.albedo = vector(data.albedo).components;
+            lowered.fuzz = data.fuzz as f32; // perceptual GGX roughness, as on the CPU
+        }
+        rtx::Material::Dielectric(data) if data.is_dispersive() => {
+            return Err(LoweringError::UnsupportedMaterial("dispersive dielectric"));
+        }
+        rtx::Material::Dielectric(data) if data.film.is_some() => {
+            return Err(LoweringError::UnsupportedMaterial("thin film dielectric"));
+        }
+        rtx::Material::Dielectric(data) if data.medium().is_some() => {
+            return Err(LoweringError::UnsupportedMaterial("absorbing dielectric"));
+        }
+        rtx::Material::Dielectric(data) => {
+            lowered.material_id = Sphere::DIELECTRIC;
+            lowered.refraction_index = data.refraction_index as f32;
+        }
+        rtx::Material::Conductor(_) => {
+            return Err(LoweringError::UnsupportedMaterial("conductor"));
+        }
+        rtx::Material::RoughDielectric(_) => {
+            return Err(LoweringError::UnsupportedMaterial("rough dielectric"));
+        }
+        rtx::Material::OrenNayar(_) => {
+            return Err(LoweringError::UnsupportedMaterial("oren-nayar"));
+        }
+        rtx::Material::Translucent(_) => {
+            return Err(LoweringError::UnsupportedMaterial("translucent"));
+        }
+        rtx::Material::Principled(_) => {
+            return Err(LoweringError::UnsupportedMaterial("principled"));
+        }
+        rtx::Material::Mix(_) => {
+            return Err(LoweringError::UnsupportedMaterial("mix"));
+        }
+        rtx::Material::Coated(_) => {
+            return Err(LoweringError::UnsupportedMaterial("coated"));
+        }
+        rtx::Material::Bump(_) => {
+            return Err(LoweringError::UnsupportedMaterial("bump mapped"));
+        }
+        rtx::Material::Cutout(_) => {
+            return Err(LoweringError::UnsupportedMaterial("alpha cutout"));
+        }
+    }
+
+    Ok(lowered)
+}
+
+fn vector(v: rtx::Vector<3>) -> Vector<3> {
+    Vector::new(v.components.map(|c| c as f32))
+}
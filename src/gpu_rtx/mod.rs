@@ -1,17 +1,19 @@
 pub mod camera;
 pub mod interval;
+pub mod lowering;
 pub mod ray;
+pub mod renderer;
 pub mod rgb;
-pub mod samples_scene;
 pub mod scene_uniforms;
 pub mod sphere;
 pub mod vector;
 
 pub use camera::Camera;
 pub use interval::Interval;
+pub use lowering::{lower, GpuScene, LoweringError};
 pub use ray::Ray;
+pub use renderer::{render, GpuError};
 pub use rgb::RGB;
-pub use samples_scene::spheres;
 pub use scene_uniforms::SceneUniforms;
 pub use sphere::Sphere;
 pub use vector::Vector;
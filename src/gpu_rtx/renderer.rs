@@ -0,0 +1,272 @@
+use std::fmt;
+
+use super::{Camera, Ray, SceneUniforms, Sphere, RGB};
+use wgpu::util::DeviceExt;
+
+const WORKGROUP_SIZE: u32 = 64;
+
+#[derive(Debug)]
+pub enum GpuError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    BufferMap(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GpuError::NoAdapter => write!(f, "no suitable GPU adapter found"),
+            GpuError::RequestDevice(err) => write!(f, "failed to create GPU device: {}", err),
+            GpuError::BufferMap(err) => write!(f, "failed to read back GPU buffer: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+impl From<wgpu::RequestDeviceError> for GpuError {
+    fn from(err: wgpu::RequestDeviceError) -> Self {
+        GpuError::RequestDevice(err)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for GpuError {
+    fn from(err: wgpu::BufferAsyncError) -> Self {
+        GpuError::BufferMap(err)
+    }
+}
+
+/// Traces `total_samples` rays per pixel through `spheres` on the GPU,
+/// `batch_size` rays per pixel at a time, and returns the averaged linear
+/// colour of every pixel row by row.
+pub async fn render(
+    camera: &Camera,
+    spheres: &[Sphere],
+    total_samples: usize,
+    batch_size: usize,
+) -> Result<Vec<RGB>, GpuError> {
+    let max_depth = camera.max_depth;
+
+    // We'll accumulate pixel colors in CPU memory
+    let mut accum_colors = vec![[0.0_f32; 3]; camera.image_width * camera.image_height];
+
+    // WGPU boilerplate
+    let instance = wgpu::Instance::default();
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions::default())
+        .await
+        .ok_or(GpuError::NoAdapter)?;
+    let (device, queue) = adapter
+        .request_device(&wgpu::DeviceDescriptor::default(), None)
+        .await?;
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("RTX Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("../shader.wgsl").into()),
+    });
+
+    let sphere_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Sphere Buffer"),
+        contents: bytemuck::cast_slice(spheres),
+        usage: wgpu::BufferUsages::STORAGE,
+    });
+
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Group Layout"),
+        entries: &[
+            // binding 0: input rays
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // binding 1: spheres
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // binding 2: output rays
+            wgpu::BindGroupLayoutEntry {
+                binding: 2,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Storage { read_only: false },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            // binding 3: uniforms
+            wgpu::BindGroupLayoutEntry {
+                binding: 3,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+        ],
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("RTX Pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: Some("main"),
+        cache: None,
+        compilation_options: Default::default(),
+    });
+
+    // We'll do ceil(total_samples / batch_size) passes, the last one
+    // covering whatever is left over.
+    // Each pass:
+    //   1) Generate that pass's rays per pixel (CPU side)
+    //   2) Ping-pong them for up to max_depth bounces (GPU side)
+    //   3) Read back the final color portion of each ray
+    //   4) Accumulate into CPU array
+    let total_samples = total_samples.max(1);
+    let batch_size = batch_size.max(1);
+    let passes = total_samples.div_ceil(batch_size);
+
+    for pass_idx in 0..passes {
+        // 1) Generate this pass's rays per pixel
+        let batch = batch_size.min(total_samples - pass_idx * batch_size);
+        let rays = camera.generate_rays_count(batch);
+        let total_rays = rays.len();
+
+        // Create GPU buffers for these rays
+        let ray_buffer_size = (total_rays * std::mem::size_of::<Ray>()) as wgpu::BufferAddress;
+
+        let mut ray_buffer_a = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ray Buffer A"),
+            contents: bytemuck::cast_slice(&rays),
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        });
+
+        let mut ray_buffer_b = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ray Buffer B"),
+            size: ray_buffer_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        // 2) Ping-pong for up to max_depth bounces
+        for bounce in 0..max_depth {
+            let uniforms = SceneUniforms {
+                pass_idx: pass_idx as u32,
+                bounce_idx: bounce as u32,
+            };
+            let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Scene Uniforms"),
+                contents: bytemuck::cast_slice(&[uniforms]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: ray_buffer_a.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: sphere_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: ray_buffer_b.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("Compute Bind Group"),
+            });
+
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Encoder"),
+            });
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Compute Pass"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&compute_pipeline);
+                pass.set_bind_group(0, &bind_group, &[]);
+                pass.dispatch_workgroups((total_rays as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+            }
+            queue.submit(Some(encoder.finish()));
+
+            std::mem::swap(&mut ray_buffer_a, &mut ray_buffer_b);
+        }
+
+        // After the loop, ray_buffer_a has the final traced rays for this batch
+        // 3) Copy them back to CPU
+        let staging_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Staging Buffer"),
+            size: ray_buffer_size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Copy Encoder"),
+        });
+        encoder.copy_buffer_to_buffer(&ray_buffer_a, 0, &staging_buffer, 0, ray_buffer_size);
+        queue.submit(Some(encoder.finish()));
+
+        let slice = staging_buffer.slice(..);
+        let (tx, rx) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |r| {
+            tx.send(r).expect("Failed to send map result");
+        });
+        device.poll(wgpu::Maintain::Wait);
+        rx.recv().expect("Failed to receive map result")?;
+
+        let data = slice.get_mapped_range();
+        let final_rays: &[Ray] = bytemuck::cast_slice(&data);
+
+        // 4) Accumulate color in CPU array
+        // each pixel had batch rays, so total_rays = (width * height * batch)
+        let mut idx = 0;
+        for pixel in accum_colors.iter_mut() {
+            // We'll just average these batch final rays
+            // Or we can sum them now and do final average at the end
+            for _ in 0..batch {
+                let c = final_rays[idx].color;
+                pixel[0] += c[0];
+                pixel[1] += c[1];
+                pixel[2] += c[2];
+                idx += 1;
+            }
+        }
+    }
+
+    // Now accum_colors contains the sum of all (total_samples) samples
+    let samples_taken = total_samples as f32;
+    Ok(accum_colors
+        .iter()
+        .map(|c| RGB::new([c[0], c[1], c[2]]) / samples_taken)
+        .collect())
+}
@@ -8,7 +8,7 @@ fn linear_to_gamma(linear_component: f32) -> f32 {
         return linear_component.sqrt();
     }
 
-    return 0.0;
+    0.0
 }
 
 impl fmt::Display for RGB {
@@ -13,3 +13,9 @@ pub struct Sphere {
     pub _pad1: f32,
     pub _pad2: f32,
 }
+
+impl Sphere {
+    pub const LAMBERTIAN: u32 = 0;
+    pub const METAL: u32 = 1;
+    pub const DIELECTRIC: u32 = 2;
+}
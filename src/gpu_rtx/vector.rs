@@ -49,11 +49,7 @@ impl<const N: usize> Vector<N> {
         if len == 0.0 {
             return Self::zero();
         }
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = self.components[i] / len;
-        }
-        Self::new(result)
+        Self::new(std::array::from_fn(|i| self.components[i] / len))
     }
 
     pub fn reflect(&self, normal: Self) -> Self {
@@ -72,11 +68,9 @@ impl<const N: usize> Add for Vector<N> {
     type Output = Self;
 
     fn add(self, other: Self) -> Self {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = self.components[i] + other.components[i];
-        }
-        Self::new(result)
+        Self::new(std::array::from_fn(|i| {
+            self.components[i] + other.components[i]
+        }))
     }
 }
 
@@ -84,11 +78,9 @@ impl<const N: usize> Sub for Vector<N> {
     type Output = Self;
 
     fn sub(self, other: Self) -> Self {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = self.components[i] - other.components[i];
-        }
-        Self::new(result)
+        Self::new(std::array::from_fn(|i| {
+            self.components[i] - other.components[i]
+        }))
     }
 }
 
@@ -96,11 +88,7 @@ impl<const N: usize> Mul<f32> for Vector<N> {
     type Output = Self;
 
     fn mul(self, scalar: f32) -> Self {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = self.components[i] * scalar;
-        }
-        Self::new(result)
+        Self::new(std::array::from_fn(|i| self.components[i] * scalar))
     }
 }
 
@@ -108,11 +96,7 @@ impl<const N: usize> Mul<Vector<N>> for f32 {
     type Output = Vector<N>;
 
     fn mul(self, vector: Vector<N>) -> Self::Output {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = vector.components[i] * self;
-        }
-        Vector::new(result)
+        Vector::new(std::array::from_fn(|i| vector.components[i] * self))
     }
 }
 
@@ -120,30 +104,19 @@ impl<const N: usize> Div<f32> for Vector<N> {
     type Output = Self;
 
     fn div(self, scalar: f32) -> Self {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = self.components[i] / scalar;
-        }
-        Self::new(result)
+        Self::new(std::array::from_fn(|i| self.components[i] / scalar))
     }
 }
 
 impl<const N: usize> Vector<N> {
     pub fn random() -> Self {
-        let mut components = [0.0; N];
-        for i in 0..N {
-            components[i] = fastrand::f32();
-        }
-        Self::new(components)
+        Self::new(std::array::from_fn(|_| fastrand::f32()))
     }
 
     pub fn random_range(range: Interval) -> Self {
-        let mut components = [0.0; N];
-        for i in 0..N {
-            components[i] = fastrand::f32() * (range.max - range.min) + range.min;
-        }
-
-        Self::new(components)
+        Self::new(std::array::from_fn(|_| {
+            fastrand::f32() * (range.max - range.min) + range.min
+        }))
     }
 }
 
@@ -151,11 +124,9 @@ impl<const N: usize> Mul<Vector<N>> for Vector<N> {
     type Output = Vector<N>;
 
     fn mul(self, vector: Vector<N>) -> Self::Output {
-        let mut result = [0.0; N];
-        for i in 0..N {
-            result[i] = vector.components[i] * self.components[i];
-        }
-        Vector::new(result)
+        Vector::new(std::array::from_fn(|i| {
+            vector.components[i] * self.components[i]
+        }))
     }
 }
 
@@ -0,0 +1,568 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::rtx::checkpoint::Fnv1a;
+use crate::{gpu_rtx, rtx::RGB};
+
+/// File formats an [`Image`] can be written as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Plain text PPM (P3), gamma corrected and clamped to 8 bits.
+    Ppm,
+    /// Portable float map, linear radiance without clamping.
+    Pfm,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "pfm" => Some(ImageFormat::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Picks a format from a file extension, if it is one we know.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+/// A rendered image in linear RGB, stored row by row from the top left.
+#[derive(Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<RGB>,
+}
+
+// Pixels are summarised by a hash of their bits. Scenes are fingerprinted
+// through their debug output, and printing every texel of a texture would
+// dwarf the rest of the scene.
+impl fmt::Debug for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut hasher = Fnv1a::new();
+        for pixel in &self.pixels {
+            for component in pixel.components {
+                hasher.write(&component.to_le_bytes());
+            }
+        }
+        f.debug_struct("Image")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("pixels", &format_args!("{:016x}", hasher.finish()))
+            .finish()
+    }
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<RGB>) -> Self {
+        assert_eq!(
+            pixels.len(),
+            width * height,
+            "pixel count does not match image size"
+        );
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Builds an image from the single precision output of the GPU backend.
+    pub fn from_gpu(width: usize, height: usize, pixels: &[gpu_rtx::RGB]) -> Self {
+        let pixels = pixels
+            .iter()
+            .map(|p| p.components.map(|c| c as f64))
+            .map(RGB::new)
+            .collect();
+        Self::new(width, height, pixels)
+    }
+
+    pub fn write(&self, out: &mut impl Write, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => self.write_ppm(out),
+            ImageFormat::Pfm => self.write_pfm(out),
+        }
+    }
+
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        self.check_writable()?;
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+        for pixel in &self.pixels {
+            writeln!(out, "{}", pixel)?;
+        }
+        Ok(())
+    }
+
+    // PFM stores rows bottom to top; a negative scale marks little endian.
+    pub fn write_pfm(&self, out: &mut impl Write) -> io::Result<()> {
+        self.check_writable()?;
+        write!(out, "PF\n{} {}\n-1.0\n", self.width, self.height)?;
+        for row in self.pixels.chunks(self.width).rev() {
+            for pixel in row {
+                for component in pixel.components {
+                    out.write_all(&(component as f32).to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Neither format holds an image without pixels; the decoders would
+    // reject the file anyway.
+    fn check_writable(&self) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot write a {}x{} image", self.width, self.height),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Reads a PPM (P3 or P6), PFM or Radiance HDR image. PPM values are
+    /// decoded with the same gamma 2 curve [`Image::write_ppm`] encodes
+    /// with, so every image comes back as linear RGB.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Self::decode(&data, true)
+    }
+
+    /// Like [`Image::load`], but PPM values are only scaled to [0, 1], as
+    /// normal and height maps store data rather than colours.
+    pub fn load_raw(path: &Path) -> io::Result<Self> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+        Self::decode(&data, false)
+    }
+
+    pub fn decode(data: &[u8], gamma: bool) -> io::Result<Self> {
+        if data.starts_with(b"#?") {
+            return decode_rgbe(data);
+        }
+        let mut header = Header::new(data);
+        let magic = header.token()?;
+        let width: usize = header.number()?;
+        let height: usize = header.number()?;
+        match magic {
+            "P3" | "P6" => {
+                let max: f64 = header.number()?;
+                if max.is_nan() || max <= 0.0 {
+                    return Err(invalid_data(format!("invalid maximum value {}", max)));
+                }
+                let count = values(width, height, 3)?;
+                let decode = |value: f64| {
+                    let value = value / max;
+                    if gamma {
+                        value * value
+                    } else {
+                        value
+                    }
+                };
+                let values: Vec<f64> = if magic == "P3" {
+                    // Every value takes at least a digit and a separator.
+                    if count > header.remaining() / 2 {
+                        return Err(truncated());
+                    }
+                    (0..count)
+                        .map(|_| header.number())
+                        .collect::<io::Result<_>>()?
+                } else {
+                    let bytes = header.binary(count)?;
+                    bytes.iter().map(|&b| b as f64).collect()
+                };
+                let pixels = values
+                    .chunks_exact(3)
+                    .map(|p| RGB::new([decode(p[0]), decode(p[1]), decode(p[2])]))
+                    .collect();
+                Ok(Self::new(width, height, pixels))
+            }
+            "PF" => {
+                let scale: f64 = header.number()?;
+                let bytes = header.binary(values(width, height, 12)?)?;
+                let value = |chunk: &[u8]| {
+                    let bytes = chunk.try_into().unwrap();
+                    if scale < 0.0 {
+                        f32::from_le_bytes(bytes) as f64
+                    } else {
+                        f32::from_be_bytes(bytes) as f64
+                    }
+                };
+                let mut rows: Vec<Vec<RGB>> = bytes
+                    .chunks_exact(width * 12)
+                    .map(|row| {
+                        row.chunks_exact(12)
+                            .map(|p| RGB::new([value(&p[0..4]), value(&p[4..8]), value(&p[8..12])]))
+                            .collect()
+                    })
+                    .collect();
+                rows.reverse();
+                Ok(Self::new(width, height, rows.concat()))
+            }
+            _ => Err(invalid_data(format!("unsupported image type '{}'", magic))),
+        }
+    }
+
+    /// Pixel at column `x`, row `y`, both clamped to the image. Black if
+    /// the image is empty.
+    pub fn pixel(&self, x: usize, y: usize) -> RGB {
+        if self.pixels.is_empty() {
+            return RGB::zero();
+        }
+        let x = x.min(self.width - 1);
+        let y = y.min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+
+    /// Writes the image to `path`, choosing the format from its extension
+    /// and falling back to PPM.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        self.save_as(path, None)
+    }
+
+    /// Like [`Image::save`], but `format` takes precedence over the extension.
+    pub fn save_as(&self, path: &Path, format: Option<ImageFormat>) -> io::Result<()> {
+        let format = format
+            .or_else(|| ImageFormat::from_path(path))
+            .unwrap_or(ImageFormat::Ppm);
+        // Checked before creating the file, so nothing is left behind.
+        self.check_writable()?;
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out, format)?;
+        out.flush()
+    }
+}
+
+// Radiance .hdr files: a text header ended by a blank line, a resolution
+// line, then 8-bit RGB mantissas sharing an exponent, each scanline flat or
+// run length encoded per channel.
+fn decode_rgbe(data: &[u8]) -> io::Result<Image> {
+    let mut position = 0;
+    let mut line = || -> io::Result<&[u8]> {
+        let start = position;
+        let end = data[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|offset| start + offset)
+            .ok_or_else(|| invalid_data("unexpected end of image header".to_string()))?;
+        position = end + 1;
+        Ok(&data[start..end])
+    };
+
+    loop {
+        let header_line = line()?;
+        if header_line.is_empty() {
+            break;
+        }
+        if header_line.starts_with(b"FORMAT=") && header_line != b"FORMAT=32-bit_rle_rgbe" {
+            return Err(invalid_data(
+                "only RGBE .hdr images are supported".to_string(),
+            ));
+        }
+    }
+    let resolution = String::from_utf8_lossy(line()?).into_owned();
+    let fields: Vec<&str> = resolution.split_whitespace().collect();
+    let (height, width) = match fields.as_slice() {
+        ["-Y", height, "+X", width] => (height.parse::<usize>(), width.parse::<usize>()),
+        _ => {
+            return Err(invalid_data(format!(
+                "unsupported .hdr orientation '{}'",
+                resolution
+            )))
+        }
+    };
+    let (Ok(height), Ok(width)) = (height, width) else {
+        return Err(invalid_data(format!("invalid .hdr size '{}'", resolution)));
+    };
+    let count = values(width, height, 1)?;
+
+    // The smallest a scanline can be: four channels of maximal runs when
+    // run length encoded, four bytes a pixel otherwise.
+    let rle = (8..0x8000).contains(&width);
+    let scanline_bytes = if rle {
+        4 + 8 * width.div_ceil(127)
+    } else {
+        4 * width
+    };
+    if height.saturating_mul(scanline_bytes) > data.len() - position {
+        return Err(truncated());
+    }
+
+    let mut bytes = data[position..].iter().copied();
+    let mut pixels = Vec::with_capacity(count);
+    let mut scanline = vec![[0u8; 4]; width];
+    for _ in 0..height {
+        let start: Vec<u8> = bytes.clone().take(4).collect();
+        let encoded = rle
+            && start.len() == 4
+            && start[0] == 2
+            && start[1] == 2
+            && ((start[2] as usize) << 8 | start[3] as usize) == width;
+        if encoded {
+            bytes.nth(3);
+            for channel in 0..4 {
+                let mut x = 0;
+                while x < width {
+                    let count = bytes.next().ok_or_else(truncated)? as usize;
+                    if count > 128 {
+                        let value = bytes.next().ok_or_else(truncated)?;
+                        for _ in 0..count - 128 {
+                            scanline.get_mut(x).ok_or_else(truncated)?[channel] = value;
+                            x += 1;
+                        }
+                    } else {
+                        for _ in 0..count {
+                            scanline.get_mut(x).ok_or_else(truncated)?[channel] =
+                                bytes.next().ok_or_else(truncated)?;
+                            x += 1;
+                        }
+                    }
+                }
+            }
+        } else {
+            for texel in &mut scanline {
+                for value in texel.iter_mut() {
+                    *value = bytes.next().ok_or_else(truncated)?;
+                }
+            }
+        }
+        pixels.extend(scanline.iter().map(|&[r, g, b, e]| {
+            if e == 0 {
+                return RGB::zero();
+            }
+            let scale = 2f64.powi(e as i32 - 136);
+            RGB::new([r, g, b].map(|c| (c as f64 + 0.5) * scale))
+        }));
+    }
+    Ok(Image::new(width, height, pixels))
+}
+
+// Number of values (or bytes) in a `width` by `height` image with
+// `per_pixel` of them a pixel, checked so that a corrupt header can neither
+// overflow it nor describe an empty image.
+fn values(width: usize, height: usize, per_pixel: usize) -> io::Result<usize> {
+    if width == 0 || height == 0 {
+        return Err(invalid_data(format!(
+            "invalid image size {}x{}",
+            width, height
+        )));
+    }
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(per_pixel))
+        .ok_or_else(|| invalid_data(format!("image size {}x{} is too large", width, height)))
+}
+
+fn truncated() -> io::Error {
+    invalid_data("image data is truncated".to_string())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Whitespace separated header fields of PPM and PFM files, followed by
+// a binary payload after exactly one whitespace byte.
+struct Header<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Header<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn token(&mut self) -> io::Result<&'a str> {
+        loop {
+            match self.data.get(self.position) {
+                Some(b'#') => {
+                    while self.data.get(self.position).is_some_and(|&b| b != b'\n') {
+                        self.position += 1;
+                    }
+                }
+                Some(b) if b.is_ascii_whitespace() => self.position += 1,
+                _ => break,
+            }
+        }
+        let start = self.position;
+        while self
+            .data
+            .get(self.position)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.position += 1;
+        }
+        if start == self.position {
+            return Err(invalid_data("unexpected end of image header".to_string()));
+        }
+        std::str::from_utf8(&self.data[start..self.position])
+            .map_err(|_| invalid_data("image header is not text".to_string()))
+    }
+
+    fn number<T: std::str::FromStr>(&mut self) -> io::Result<T> {
+        let token = self.token()?;
+        token
+            .parse()
+            .map_err(|_| invalid_data(format!("invalid number '{}' in image", token)))
+    }
+
+    fn binary(&mut self, length: usize) -> io::Result<&'a [u8]> {
+        let start = self.position + 1;
+        let end = start.checked_add(length).ok_or_else(truncated)?;
+        self.data.get(start..end).ok_or_else(truncated)
+    }
+
+    fn remaining(&self) -> usize {
+        self.data.len() - self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two pixels, red and green, with 51 on the red one's blue channel.
+    const P3: &[u8] = b"P3\n# made by hand\n2 1\n255\n255 0 51\n0 255 0\n";
+    const P6: &[u8] = b"P6\n2 1\n255\n\xff\x00\x33\x00\xff\x00";
+
+    // One column of two pixels, little endian and stored bottom row first:
+    // (1, 0.5, 0.25) at the bottom and (2, 0, 0) on top.
+    const PFM: &[u8] = b"PF\n1 2\n-1.0\n\
+        \x00\x00\x80\x3f\x00\x00\x00\x3f\x00\x00\x80\x3e\
+        \x00\x00\x00\x40\x00\x00\x00\x00\x00\x00\x00\x00";
+
+    // A flat scanline of one lit and one black pixel, then a run length
+    // encoded one of eight lit pixels. Mantissas 128, 64 and 0 share the
+    // exponent 129, i.e. a scale of 2^-7.
+    const RGBE_FLAT: &[u8] = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n\
+        \x80\x40\x00\x81\x00\x00\x00\x00";
+    const RGBE_RLE: &[u8] = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 8\n\
+        \x02\x02\x00\x08\x88\x80\x88\x40\x88\x00\x88\x81";
+
+    const LIT_RGBE: [f64; 3] = [128.5 / 128.0, 64.5 / 128.0, 0.5 / 128.0];
+
+    fn is_invalid(result: io::Result<Image>) -> bool {
+        matches!(result, Err(err) if err.kind() == io::ErrorKind::InvalidData)
+    }
+
+    #[test]
+    fn decodes_text_and_binary_ppm() {
+        for data in [P3, P6] {
+            let image = Image::decode(data, false).unwrap();
+            assert_eq!((image.width, image.height), (2, 1));
+            assert_eq!(image.pixel(0, 0).components, [1.0, 0.0, 0.2]);
+            assert_eq!(image.pixel(1, 0).components, [0.0, 1.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn applies_gamma_to_ppm() {
+        let image = Image::decode(P3, true).unwrap();
+        let [r, _, b] = image.pixel(0, 0).components;
+        assert_eq!(r, 1.0);
+        assert!((b - 0.04).abs() < 1e-12);
+    }
+
+    #[test]
+    fn decodes_pfm_top_row_first() {
+        let image = Image::decode(PFM, true).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.pixel(0, 0).components, [2.0, 0.0, 0.0]);
+        assert_eq!(image.pixel(0, 1).components, [1.0, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn round_trips_through_pfm() {
+        let image = Image::decode(PFM, true).unwrap();
+        let mut data = Vec::new();
+        image.write_pfm(&mut data).unwrap();
+        assert_eq!(Image::decode(&data, true).unwrap(), image);
+    }
+
+    #[test]
+    fn decodes_flat_and_run_length_encoded_rgbe() {
+        let flat = Image::decode(RGBE_FLAT, true).unwrap();
+        assert_eq!(flat.pixel(0, 0).components, LIT_RGBE);
+        assert_eq!(flat.pixel(1, 0).components, [0.0; 3]);
+
+        let rle = Image::decode(RGBE_RLE, true).unwrap();
+        assert_eq!((rle.width, rle.height), (8, 1));
+        assert!(rle.pixels.iter().all(|p| p.components == LIT_RGBE));
+    }
+
+    #[test]
+    fn rejects_empty_images() {
+        assert!(is_invalid(Image::decode(b"P3\n0 1\n255\n", true)));
+        assert!(is_invalid(Image::decode(b"P6\n1 0\n255\n", true)));
+        assert!(is_invalid(Image::decode(b"PF\n0 1\n-1.0\n", true)));
+        assert!(is_invalid(Image::decode(
+            b"#?RADIANCE\n\n-Y 0 +X 8\n",
+            true
+        )));
+    }
+
+    #[test]
+    fn rejects_sizes_the_data_cannot_hold() {
+        let huge = b"P6\n18446744073709551615 18446744073709551615\n255\n\x00";
+        assert!(is_invalid(Image::decode(huge, true)));
+        let huge = b"PF\n4611686018427387904 2\n-1.0\n\x00";
+        assert!(is_invalid(Image::decode(huge, true)));
+        let huge = b"#?RADIANCE\n\n-Y 1000000 +X 1000000\n\x00\x00\x00\x00";
+        assert!(is_invalid(Image::decode(huge, true)));
+        assert!(is_invalid(Image::decode(
+            b"P3\n1000000 1000000\n255\n1 2 3\n",
+            true
+        )));
+        assert!(is_invalid(Image::decode(&P6[..P6.len() - 1], true)));
+        assert!(is_invalid(Image::decode(&PFM[..PFM.len() - 1], true)));
+        assert!(is_invalid(Image::decode(
+            &RGBE_RLE[..RGBE_RLE.len() - 1],
+            true
+        )));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(is_invalid(Image::decode(b"P3\n1 1\n0\n0 0 0\n", true)));
+        assert!(is_invalid(Image::decode(b"P3\n1 x\n255\n", true)));
+        assert!(is_invalid(Image::decode(b"P5\n1 1\n255\n\x00", true)));
+        assert!(is_invalid(Image::decode(
+            b"#?RADIANCE\n\n+Y 1 +X 1\n",
+            true
+        )));
+    }
+
+    #[test]
+    fn empty_images_read_as_black() {
+        let image = Image::new(0, 0, Vec::new());
+        assert_eq!(image.pixel(3, 4).components, [0.0; 3]);
+    }
+
+    #[test]
+    fn refuses_to_write_empty_images() {
+        for (width, height) in [(0, 0), (0, 3), (3, 0)] {
+            let image = Image::new(width, height, Vec::new());
+            for format in [ImageFormat::Ppm, ImageFormat::Pfm] {
+                let mut out = Vec::new();
+                let err = image.write(&mut out, format).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+                assert!(out.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn debug_output_summarises_pixels() {
+        let image = Image::new(512, 256, vec![RGB::new([0.25, 0.5, 1.0]); 512 * 256]);
+        let mut changed = image.clone();
+        changed.pixels[1000].components[1] = 0.75;
+        let debug = format!("{:?}", image);
+        assert!(debug.len() < 100, "{}", debug);
+        assert_ne!(debug, format!("{:?}", changed));
+    }
+}
@@ -0,0 +1,49 @@
+//! A small path tracer with a CPU backend ([`rtx`]) and a wgpu compute
+//! backend ([`gpu_rtx`]).
+//!
+//! A scene is a [`rtx::Camera`] plus a [`rtx::Hittable`] world built from
+//! [`rtx::Target`]s, optionally lit by [`rtx::Lighting`]. Rendering returns linear RGB pixels that
+//! [`image::Image`] writes out as PPM or PFM:
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::sync::Arc;
+//!
+//! use rtrace::image::Image;
+//! use rtrace::rtx::{
+//!     Camera, Hittable, LambertianData, Material, RenderControl, Scene, SphereData, Target,
+//!     TargetList, TileOptions, Vector, RGB,
+//! };
+//!
+//! let camera = Camera::new(
+//!     320,
+//!     240,
+//!     50,
+//!     10,
+//!     40.0,
+//!     Vector::new([0.0, 1.0, 5.0]),
+//!     Vector::new([0.0, 0.0, 0.0]),
+//!     Vector::new([0.0, 1.0, 0.0]),
+//!     0.0,
+//!     5.0,
+//! );
+//!
+//! let mut world = TargetList::new();
+//! world.add(Target::Sphere(SphereData::new(
+//!     Vector::new([0.0, 0.0, 0.0]),
+//!     1.0,
+//!     Material::Lambertian(LambertianData::new(RGB::new([0.8, 0.3, 0.3]))),
+//! )));
+//!
+//! let scene = Scene::new(camera, Arc::new(Hittable::Multiple(world)));
+//! let output = scene.render_mt(&TileOptions::default(), &RenderControl::default());
+//! let (width, height) = (scene.camera.image_width, scene.camera.image_height);
+//! Image::new(width, height, output.image)
+//!     .save(Path::new("out.ppm"))
+//!     .unwrap();
+//! ```
+
+pub mod cli;
+pub mod gpu_rtx;
+pub mod image;
+pub mod rtx;
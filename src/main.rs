@@ -0,0 +1,5 @@
+use rtrace::cli::{self, Backend};
+
+fn main() {
+    cli::main(Backend::MultiThread);
+}
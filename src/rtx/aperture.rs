@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use super::distribution::Distribution2D;
+use crate::image::Image;
+
+// Shape of the lens opening, which out of focus highlights take on.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Aperture {
+    #[default]
+    Circle,
+    // Regular, with its corners on the unit circle.
+    Polygon {
+        blades: usize,
+        rotation: f64, // radians
+    },
+    Mask(Arc<ApertureMask>),
+}
+
+impl Aperture {
+    // Blade counts below three are round.
+    pub fn polygon(blades: usize, rotation: f64) -> Self {
+        if blades < 3 {
+            return Aperture::Circle;
+        }
+        Aperture::Polygon { blades, rotation }
+    }
+
+    // A point spread evenly over the opening, inside the square from -1 to
+    // 1 with y up. Scaled by the defocus radius like the round disk.
+    pub fn sample(&self) -> (f64, f64) {
+        match self {
+            Aperture::Circle => loop {
+                let (x, y) = (fastrand::f64() * 2.0 - 1.0, fastrand::f64() * 2.0 - 1.0);
+                if x * x + y * y < 1.0 {
+                    return (x, y);
+                }
+            },
+            Aperture::Polygon { blades, rotation } => {
+                // The polygon is a fan of equal triangles around the centre.
+                let step = 2.0 * PI / *blades as f64;
+                let blade = fastrand::usize(..*blades) as f64;
+                let (a, b) = (rotation + blade * step, rotation + (blade + 1.0) * step);
+                let (mut s, mut t) = (fastrand::f64(), fastrand::f64());
+                if s + t > 1.0 {
+                    (s, t) = (1.0 - s, 1.0 - t);
+                }
+                // Rotation zero puts a vertex straight up.
+                let corner = |angle: f64| (-angle.sin(), angle.cos());
+                let ((ax, ay), (bx, by)) = (corner(a), corner(b));
+                (s * ax + t * bx, s * ay + t * by)
+            }
+            Aperture::Mask(mask) => mask.sample(),
+        }
+    }
+}
+
+// An aperture drawn as an image, light where it lets light through. The
+// image fills the square the round aperture fits in.
+#[derive(Clone, PartialEq)]
+pub struct ApertureMask {
+    pub image: Arc<Image>,
+    distribution: Distribution2D,
+}
+
+// The sampling table follows from the image, so it is left out.
+impl fmt::Debug for ApertureMask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApertureMask")
+            .field("image", &self.image)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ApertureMask {
+    // `None` if no pixel lets light through, since there is no shape to
+    // sample then.
+    pub fn new(image: Arc<Image>) -> Option<Self> {
+        let weights: Vec<f64> = image
+            .pixels
+            .iter()
+            .map(|pixel| pixel.components.iter().sum::<f64>().max(0.0) / 3.0)
+            .collect();
+        if !weights.iter().any(|&weight| weight > 0.0) {
+            return None;
+        }
+        let distribution = Distribution2D::new(&weights, image.width, image.height);
+        Some(Self {
+            image,
+            distribution,
+        })
+    }
+
+    fn sample(&self) -> (f64, f64) {
+        let ((u, v), _) = self.distribution.sample(fastrand::f64(), fastrand::f64());
+        (2.0 * u - 1.0, 1.0 - 2.0 * v)
+    }
+}
@@ -0,0 +1,115 @@
+use super::{Hit, Material, Medium, Ray, ScatterResult, Texture, Vector, RGB};
+
+const BUMP_DELTA: f64 = 0.0005; // finite difference step in (u, v)
+
+// How a map perturbs the shading normal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShadingMap {
+    Normal { texture: Texture, strength: f64 }, // tangent space, rgb = (xyz + 1) / 2
+    Height { texture: Texture, scale: f64 },    // displacement along the normal
+}
+
+// Any material with a normal or bump map. The wrapped material shades a
+// copy of the hit whose normal has been perturbed using the surface
+// tangents, so geometry stays untouched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BumpData {
+    pub base: Material,
+    pub map: ShadingMap,
+}
+
+impl BumpData {
+    pub fn new(base: Material, map: ShadingMap) -> Self {
+        Self { base, map }
+    }
+
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        self.base.scatter(incoming, &self.shade(incoming, hit))
+    }
+
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        self.base
+            .eval(incoming, &self.shade(incoming, hit), direction)
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        self.base
+            .pdf(incoming, &self.shade(incoming, hit), direction)
+    }
+
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        self.base.emitted(hit)
+    }
+
+    pub fn medium(&self) -> Option<Medium> {
+        self.base.medium()
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.base.is_dispersive()
+    }
+
+    // The hit with its shading normal perturbed. Surfaces without tangents,
+    // and normals that would turn away from the viewer, are left alone.
+    fn shade<'a>(&self, incoming: Ray, hit: &Hit<'a>) -> Hit<'a> {
+        let n = hit.outward_normal();
+        let perturbed = match &self.map {
+            ShadingMap::Normal { texture, strength } => normal_map(hit, n, texture, *strength),
+            ShadingMap::Height { texture, scale } => height_map(hit, n, texture, *scale),
+        };
+        let Some(perturbed) = perturbed else {
+            return *hit;
+        };
+
+        let normal = if hit.front_face {
+            perturbed
+        } else {
+            -1.0 * perturbed
+        };
+        if normal.dot(incoming.direction) >= 0.0 {
+            return *hit;
+        }
+        Hit { normal, ..*hit }
+    }
+}
+
+fn normal_map(hit: &Hit, n: Vector<3>, texture: &Texture, strength: f64) -> Option<Vector<3>> {
+    let tangent = hit.dpdu - n.dot(hit.dpdu) * n;
+    if tangent.near_zero() {
+        return None;
+    }
+    let tangent = tangent.normalize();
+    let mut bitangent = n.cross(tangent);
+    if bitangent.dot(hit.dpdv) < 0.0 {
+        bitangent = -1.0 * bitangent;
+    }
+
+    let [x, y, z] = texture
+        .value(hit.u, hit.v, hit.p)
+        .components
+        .map(|c| 2.0 * c - 1.0);
+    let perturbed = (strength * x) * tangent + (strength * y) * bitangent + z * n;
+    (!perturbed.near_zero()).then(|| perturbed.normalize())
+}
+
+// Bump mapping after Blinn: offsets the tangents by the slope of the
+// displacement, ignoring the curvature term.
+fn height_map(hit: &Hit, n: Vector<3>, texture: &Texture, scale: f64) -> Option<Vector<3>> {
+    let (u, v, p) = (hit.u, hit.v, hit.p);
+    let height = scale * texture.scalar(u, v, p);
+    let du = scale * texture.scalar(u + BUMP_DELTA, v, p + BUMP_DELTA * hit.dpdu);
+    let dv = scale * texture.scalar(u, v + BUMP_DELTA, p + BUMP_DELTA * hit.dpdv);
+
+    let dpdu = hit.dpdu + ((du - height) / BUMP_DELTA) * n;
+    let dpdv = hit.dpdv + ((dv - height) / BUMP_DELTA) * n;
+    let perturbed = dpdu.cross(dpdv);
+    if perturbed.near_zero() {
+        return None;
+    }
+    let perturbed = perturbed.normalize();
+    Some(if perturbed.dot(n) < 0.0 {
+        -1.0 * perturbed
+    } else {
+        perturbed
+    })
+}
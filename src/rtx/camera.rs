@@ -1,42 +1,64 @@
-use super::{Hittable, HittableObject, Interval, Ray, Vector, RGB};
-use std::sync::Arc;
-use threadpool::ThreadPool;
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct RenderTask {
-    start_row: usize,
-    end_row: usize,
+use super::checkpoint::{fingerprint, pass_seed};
+use super::tile::{self, Tile, TileOptions};
+use super::{
+    stats, Aperture, Checkpoint, CheckpointError, CheckpointOptions, Hittable, HittableObject,
+    Interval, LensSystem, Lighting, MediumStack, Projection, Ray, RenderControl, RenderStats,
+    SampledWavelengths, Stereo, Vector, RGB,
+};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOutput {
+    pub image: Vec<RGB>,
+    pub stats: RenderStats,
+    pub cancelled: bool, // true if the render stopped before every pixel was done
 }
 
-impl RenderTask {
-    pub fn new(start_row: usize, end_row: usize) -> Self {
-        Self { start_row, end_row }
+impl RenderOutput {
+    pub fn new(image: Vec<RGB>, stats: RenderStats, cancelled: bool) -> Self {
+        Self {
+            image,
+            stats,
+            cancelled,
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Camera {
-    pub aspect_ratio: f64,        // Ratio of image width to height
-    pub image_width: usize,       // Rendered image width in pixels
-    pub image_height: usize,      // Rendered image height in pixels
-    pub samples_per_pixel: usize, // antialiasing
-    pub max_depth: usize,         // max bounces of a ray
-    pub vfov: f64,                // vertical field of view
-    pub lookfrom: Vector<3>,      // camera position
-    pub lookat: Vector<3>,        // camera target
-    pub vup: Vector<3>,           // camera relative up direction
-    pub defocus_angle: f64,       // variation angle of rays through each pixel
-    pub focus_dist: f64,          // distance from camera lookfrom point to plane of perfect focus
-    center: Vector<3>,            // Camera center
-    pixel00_loc: Vector<3>,       // Location of pixel (0, 0)
-    pixel_delta_u: Vector<3>,     // Horizontal delta to the next pixel
-    pixel_delta_v: Vector<3>,     // Vertical delta to the next pixel,
-    pixel_sample_scale: f64,      // Color scale factor for sum of pixels
-    defocus_disk_u: Vector<3>,    // defocus disk horizontal radius
-    defocus_disk_v: Vector<3>,    // defocus disk vertical radius
+    pub aspect_ratio: f64,             // Ratio of image width to height
+    pub image_width: usize,            // Rendered image width in pixels
+    pub image_height: usize,           // Rendered image height in pixels
+    pub samples_per_pixel: usize,      // antialiasing
+    pub max_depth: usize,              // max bounces of a ray
+    pub vfov: f64,                     // vertical field of view
+    pub lookfrom: Vector<3>,           // camera position
+    pub lookat: Vector<3>,             // camera target
+    pub vup: Vector<3>,                // camera relative up direction
+    pub defocus_angle: f64,            // variation angle of rays through each pixel
+    pub focus_dist: f64, // distance from camera lookfrom point to plane of perfect focus
+    pub spectral: bool,  // trace sampled wavelengths so dielectrics can disperse
+    pub projection: Projection, // how image positions map to rays
+    pub stereo: Option<Stereo>, // render both eyes into the image
+    pub lens: Option<Arc<LensSystem>>, // trace through real optics instead of projecting
+    pub aperture: Aperture, // shape of the defocus disk
+    center: Vector<3>,   // Camera center
+    u: Vector<3>,        // camera frame: right,
+    v: Vector<3>,        // up,
+    w: Vector<3>,        // and opposite the view direction
+    half_height: f64,    // half the viewport height at unit distance
+    pixel_sample_scale: f64, // Color scale factor for sum of pixels
+    defocus_disk_u: Vector<3>, // defocus disk horizontal radius
+    defocus_disk_v: Vector<3>, // defocus disk vertical radius
 }
 
 impl Camera {
+    /// `vfov` and `defocus_angle` are in degrees; a zero defocus angle gives
+    /// a pinhole camera focused everywhere.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         image_width: usize,
         image_height: usize,
@@ -53,23 +75,12 @@ impl Camera {
         let center = lookfrom;
 
         let theta = vfov.to_radians();
-        let h = (theta / 2.0).tan();
-        let viewport_height = 2.0 * h * focus_dist;
-        let viewport_width = viewport_height * aspect_ratio;
+        let half_height = (theta / 2.0).tan();
 
         let w = (lookfrom - lookat).normalize();
         let u = vup.cross(w).normalize();
         let v = w.cross(u);
 
-        let viewport_u = viewport_width * u;
-        let viewport_v = viewport_height * (-1.0 * v);
-
-        let pixel_delta_u = viewport_u / image_width as f64;
-        let pixel_delta_v = viewport_v / image_height as f64;
-
-        let viewport_upper_left = center - (focus_dist * w) - viewport_u / 2.0 - viewport_v / 2.0;
-        let pixel00_loc = viewport_upper_left + 0.5 * (pixel_delta_u + pixel_delta_v);
-
         let pixel_sample_scale = 1.0 / samples_per_pixel as f64;
 
         let defocus_radius = focus_dist * (defocus_angle / 2.0).to_radians().tan();
@@ -81,9 +92,10 @@ impl Camera {
             image_width,
             image_height,
             center,
-            pixel00_loc,
-            pixel_delta_u,
-            pixel_delta_v,
+            u,
+            v,
+            w,
+            half_height,
             samples_per_pixel,
             pixel_sample_scale,
             max_depth,
@@ -95,130 +107,436 @@ impl Camera {
             defocus_disk_u,
             defocus_disk_v,
             focus_dist,
+            spectral: false,
+            projection: Projection::Perspective,
+            stereo: None,
+            lens: None,
+            aperture: Aperture::Circle,
         }
     }
 
-    fn get_ray(&self, i: usize, j: usize) -> Ray {
-        let offset = Vector::new([fastrand::f64() - 0.5, fastrand::f64() - 0.5]);
-        let pixel_sample = self.pixel00_loc
-            + ((i as f64 + offset.components[0]) * self.pixel_delta_u)
-            + ((j as f64 + offset.components[1]) * self.pixel_delta_v);
-        let origin = if self.defocus_angle <= 0.0 {
-            self.center
-        } else {
-            self.defocus_disk_sample()
+    // `None` for pixels outside a fisheye's image circle or rays the lens
+    // blocks. Only perspective rays are defocused.
+    fn get_ray(&self, i: usize, j: usize) -> Option<Ray> {
+        let s = (i as f64 + fastrand::f64()) / self.image_width as f64;
+        let t = (j as f64 + fastrand::f64()) / self.image_height as f64;
+        if let Some(lens) = &self.lens {
+            let (origin, direction) = lens.ray(s, t, self.aspect_ratio)?;
+            let to_world = |[x, y, z]: [f64; 3]| x * self.u + y * self.v + z * self.w;
+            return Some(Ray::new(
+                self.center + to_world(origin.components),
+                to_world(direction.components),
+            ));
+        }
+        let (eye, s, t, aspect_ratio) = match self.stereo {
+            Some(stereo) => {
+                let (eye, s, t, scale) = stereo.split(s, t);
+                (eye, s, t, self.aspect_ratio * scale)
+            }
+            None => (0.0, s, t, self.aspect_ratio),
+        };
+
+        // The ray from between the eyes, and how far along it to go to
+        // reach a given distance from the camera.
+        let x = (2.0 * s - 1.0) * aspect_ratio * self.half_height;
+        let y = (1.0 - 2.0 * t) * self.half_height;
+        let (origin, direction) = match self.projection {
+            Projection::Perspective => (self.center, x * self.u + y * self.v - self.w),
+            Projection::Orthographic => (
+                self.center + self.focus_dist * (x * self.u + y * self.v),
+                -1.0 * self.w,
+            ),
+            projection => {
+                let [x, y, z] = projection
+                    .direction(s, t, aspect_ratio, self.vfov.to_radians())?
+                    .components;
+                (self.center, x * self.u + y * self.v + z * self.w)
+            }
+        };
+        let depth = |direction: Vector<3>| match self.projection {
+            Projection::Perspective | Projection::Orthographic => direction.dot(-1.0 * self.w),
+            _ => direction.length(),
+        };
+
+        // Each eye is moved sideways and turned to meet the other at the
+        // convergence distance. Panoramic eyes straddle every direction,
+        // closing up towards the poles.
+        let (origin, direction) = match self.stereo {
+            Some(stereo) if eye != 0.0 => {
+                let across = match self.projection {
+                    Projection::Perspective | Projection::Orthographic => self.u,
+                    _ => direction.normalize().cross(self.v),
+                };
+                let target = origin + direction * (stereo.convergence / depth(direction));
+                let origin = origin + (eye * stereo.interocular / 2.0) * across;
+                (origin, target - origin)
+            }
+            _ => (origin, direction),
         };
-        let direction = pixel_sample - origin;
 
-        Ray::new(origin, direction.normalize())
+        if self.projection == Projection::Perspective && self.defocus_angle > 0.0 {
+            let focus = origin + direction * (self.focus_dist / depth(direction));
+            let origin = self.defocus_disk_sample(origin);
+            return Some(Ray::new(origin, (focus - origin).normalize()));
+        }
+        Some(Ray::new(origin, direction.normalize()))
     }
 
-    fn defocus_disk_sample(&self) -> Vector<3> {
-        let p = Vector::<3>::random_in_unit_disk();
-        self.center
-            + (p.components[0] * self.defocus_disk_u)
-            + (p.components[1] * self.defocus_disk_v)
+    fn defocus_disk_sample(&self, center: Vector<3>) -> Vector<3> {
+        let (x, y) = self.aperture.sample();
+        center + (x * self.defocus_disk_u) + (y * self.defocus_disk_v)
     }
 
-    fn ray_color(&self, ray: Ray, world: Arc<Hittable>, depth: usize) -> RGB {
-        if depth <= 0 {
+    fn ray_color(
+        &self,
+        ray: Ray,
+        world: Arc<Hittable>,
+        lighting: &Lighting,
+        depth: usize,
+        path: &mut PathState,
+    ) -> RGB {
+        if depth == 0 {
             return RGB::new([0.0, 0.0, 0.0]);
         }
+        stats::record_ray();
 
         match world.hit(ray, Interval::new(0.001, f64::INFINITY)) {
             Some(hit) => {
-                let scatter = hit.material.scatter(ray, hit);
-                scatter.attenuation * self.ray_color(scatter.scattered, world, depth - 1)
-            }
-            None => {
-                let direction = ray.direction.normalize();
-                let t = 0.5 * (direction.components[1] + 1.0);
-                (1.0 - t) * RGB::new([1.0, 1.0, 1.0]) + t * RGB::new([0.5, 0.7, 1.0])
+                let transmittance = match path.media.current() {
+                    Some(medium) => medium.transmittance(hit.t * ray.direction.length()),
+                    None => RGB::new([1.0, 1.0, 1.0]),
+                };
+                if hit.material.is_dispersive() {
+                    if let Some(wavelengths) = &mut path.wavelengths {
+                        wavelengths.terminate_secondary();
+                    }
+                }
+                let emitted = lighting.emitted(&hit, ray.origin, path.normal, path.bsdf_pdf);
+                let Some(scatter) = hit.material.scatter(ray, &hit) else {
+                    return transmittance * emitted;
+                };
+                let scattered = scatter.scattered.with_wavelength(ray.wavelength);
+                // Delta lobes never see lights, so this is the same for
+                // whichever lobe was sampled.
+                let direct = lighting.direct(&world, ray, &hit);
+                path.bsdf_pdf = (!scatter.specular).then_some(scatter.pdf);
+                path.normal = hit.normal;
+
+                // Refraction through a closed surface moves the path into or
+                // out of the medium behind it.
+                let front_face = hit.front_face;
+                let crossing = match hit.material.medium() {
+                    Some(medium) if scatter.scattered.direction.dot(hit.normal) < 0.0 => {
+                        Some(medium)
+                    }
+                    _ => None,
+                };
+                if let Some(medium) = crossing {
+                    if front_face {
+                        path.media.enter(medium);
+                    } else {
+                        path.media.exit(medium);
+                    }
+                }
+
+                let color = self.ray_color(scattered, world, lighting, depth - 1, path);
+
+                if let Some(medium) = crossing {
+                    if front_face {
+                        path.media.exit(medium);
+                    } else {
+                        path.media.enter(medium);
+                    }
+                }
+                transmittance * (emitted + direct + scatter.attenuation * color)
             }
+            None => lighting.escaped(ray.direction, path.bsdf_pdf),
         }
     }
 
-    pub fn render_st(&self, world: Arc<Hittable>) -> Vec<RGB> {
+    /// Renders on the calling thread, reporting each scanline as a tile.
+    pub fn render_st(
+        &self,
+        world: Arc<Hittable>,
+        lighting: &Lighting,
+        control: &RenderControl,
+    ) -> RenderOutput {
+        let start = Instant::now();
+        let mut stats = RenderStats::new();
         let mut image = Vec::<RGB>::new();
+        let mut cancelled = false;
+        stats::take_counters();
 
         for y in 0..self.image_height {
+            if control.cancel.is_cancelled() {
+                cancelled = true;
+                break;
+            }
             let world = world.clone();
-            eprint!("\rScanlines remaining: {}", self.image_height - y);
-            let mut row = self.render_row(world, y);
+            let mut row = self.render_row(world, lighting, y);
             image.append(&mut row);
+
+            let tile = Tile::new(0, self.image_width, y, y + 1);
+            let progress = (y + 1) as f64 / self.image_height as f64;
+            control.observer.tile_completed(&tile, progress);
         }
+        image.resize(self.image_width * self.image_height, RGB::zero());
 
-        eprintln!("\nDone.");
-        image
+        stats.add_counters(stats::take_counters());
+        stats.add_phase(stats::RENDER_PHASE, start.elapsed());
+        control.observer.finished(&stats);
+        RenderOutput::new(image, stats, cancelled)
     }
 
+    /// Renders with `options.num_threads` workers pulling tiles.
     pub fn render_mt(
         &self,
         world: Arc<Hittable>,
-        num_threads: usize,
-        rows_per_task: usize,
-    ) -> Vec<RGB> {
-        use std::sync::mpsc::channel;
-
-        let pool = ThreadPool::new(num_threads);
-        let (tx, rx) = channel();
-
-        let mut tasks = Vec::new();
-        let mut current_row = 0;
-
-        while current_row < self.image_height {
-            let start_row = current_row;
-            let end_row = (current_row + rows_per_task).min(self.image_height);
-            tasks.push(RenderTask::new(start_row, end_row));
-            current_row = end_row;
-        }
+        lighting: &Lighting,
+        options: &TileOptions,
+        control: &RenderControl,
+    ) -> RenderOutput {
+        let start = Instant::now();
+        let mut stats = RenderStats::new();
+
+        let (image, completed) = self.render_tiles(
+            options,
+            control,
+            &mut stats,
+            (0.0, 1.0),
+            |index, tile, pixels| {
+                // Seeding per tile rather than per worker keeps a seeded
+                // render the same whichever thread picks each tile up.
+                if let Some(seed) = options.seed {
+                    fastrand::seed(pass_seed(seed, 0, index));
+                }
+                let mut i = 0;
+                for y in tile.start_y..tile.end_y {
+                    for x in tile.start_x..tile.end_x {
+                        pixels[i] = self.render_pixel(world.clone(), lighting, y, x);
+                        i += 1;
+                    }
+                }
+            },
+        );
 
-        for task in tasks.clone() {
-            let tx = tx.clone();
-            let camera = self.clone();
-            let world = world.clone();
+        stats.add_phase(stats::RENDER_PHASE, start.elapsed());
+        control.observer.finished(&stats);
+        RenderOutput::new(image, stats, !completed)
+    }
 
-            pool.execute(move || {
-                let mut rows = Vec::new();
-                for y in task.start_row..task.end_row {
-                    rows.push((y, camera.render_row(world.clone(), y)));
-                }
-                tx.send(rows).expect("Failed to send task results");
-            });
-        }
+    /// Cancelling discards the pass in flight and writes a checkpoint of the
+    /// passes completed so far, so the render can be resumed later.
+    pub fn render_progressive(
+        &self,
+        world: Arc<Hittable>,
+        lighting: &Lighting,
+        tile_options: &TileOptions,
+        options: &CheckpointOptions,
+        control: &RenderControl,
+    ) -> Result<RenderOutput, CheckpointError> {
+        let start = Instant::now();
+        let mut stats = RenderStats::new();
+        let scene_hash = fingerprint(&(&world, lighting));
+        // The sample count is left out so a finished render can be resumed
+        // with a higher one to refine it.
+        let camera_hash = fingerprint(&Camera {
+            samples_per_pixel: 0,
+            ..self.clone()
+        });
+        let samples_per_pass = options.samples_per_pass.max(1);
+
+        let mut checkpoint = if options.resume && options.path.exists() {
+            let checkpoint = Checkpoint::load(&options.path)?;
+            checkpoint.validate(
+                scene_hash,
+                camera_hash,
+                samples_per_pass,
+                self.samples_per_pixel,
+            )?;
+            control
+                .observer
+                .resumed(&options.path, checkpoint.passes_completed);
+            checkpoint
+        } else {
+            Checkpoint::new(
+                self.image_width,
+                self.image_height,
+                scene_hash,
+                camera_hash,
+                options.seed,
+                samples_per_pass,
+            )
+        };
+        stats.add_phase("setup", start.elapsed());
+
+        let remaining = self.samples_per_pixel - checkpoint.samples_completed();
+        let passes = checkpoint.passes_completed + remaining.div_ceil(samples_per_pass);
+        let mut last_save = Instant::now();
+
+        while checkpoint.passes_completed < passes {
+            let pass_start = Instant::now();
+            let pass = checkpoint.passes_completed;
+            let seed = checkpoint.seed;
+            let samples =
+                samples_per_pass.min(self.samples_per_pixel - checkpoint.samples_completed());
+            let progress = (pass as f64 / passes as f64, 1.0 / passes as f64);
+
+            // Each tile is seeded from (seed, pass, tile) so a resumed render
+            // continues the same random sequence it would have produced
+            // uninterrupted.
+            let (sums, completed) = self.render_tiles(
+                tile_options,
+                control,
+                &mut stats,
+                progress,
+                |index, tile, pixels| {
+                    fastrand::seed(pass_seed(seed, pass, index));
+                    let mut i = 0;
+                    for y in tile.start_y..tile.end_y {
+                        for x in tile.start_x..tile.end_x {
+                            pixels[i] = self.sample_pixel(world.clone(), lighting, y, x, samples);
+                            i += 1;
+                        }
+                    }
+                },
+            );
+            stats.add_phase(stats::RENDER_PHASE, pass_start.elapsed());
+            if !completed {
+                break;
+            }
 
-        // Collect rows from threads
-        let mut row_buffers = vec![Vec::new(); self.image_height];
-        for _ in 0..tasks.len() {
-            let rows = rx.recv().expect("Failed to receive task results");
-            for (y, row) in rows {
-                row_buffers[y] = row;
+            for (i, sum) in sums.into_iter().enumerate() {
+                checkpoint.accumulation[i] = checkpoint.accumulation[i] + sum;
+                checkpoint.sample_counts[i] += samples as u32;
+            }
+            checkpoint.passes_completed += 1;
+
+            if last_save.elapsed() >= options.interval {
+                let save_start = Instant::now();
+                checkpoint.save(&options.path)?;
+                stats.add_phase("checkpoint", save_start.elapsed());
+                last_save = Instant::now();
             }
         }
 
-        // Flatten rows into a single vector
-        row_buffers.into_iter().flatten().collect()
+        let save_start = Instant::now();
+        checkpoint.save(&options.path)?;
+        stats.add_phase("checkpoint", save_start.elapsed());
+
+        control.observer.finished(&stats);
+        let cancelled = checkpoint.passes_completed < passes;
+        Ok(RenderOutput::new(checkpoint.image(), stats, cancelled))
+    }
+
+    // Hands tiles out to a fixed set of workers through a shared counter, so
+    // a thread that finishes a cheap tile immediately picks up the next one.
+    // Each worker shades into its own scratch buffer and copies the finished
+    // tile into the shared framebuffer. Workers stop picking up tiles once
+    // the render is cancelled; the returned flag is true if every tile was
+    // rendered. `progress` is the (start, span) of the overall render this
+    // call covers, used to scale what the observer sees.
+    fn render_tiles<F>(
+        &self,
+        options: &TileOptions,
+        control: &RenderControl,
+        stats: &mut RenderStats,
+        progress: (f64, f64),
+        shade_tile: F,
+    ) -> (Vec<RGB>, bool)
+    where
+        F: Fn(usize, &Tile, &mut [RGB]) + Sync,
+    {
+        let tiles = tile::tiles(self.image_width, self.image_height, options);
+        let next_tile = AtomicUsize::new(0);
+        let completed_tiles = AtomicUsize::new(0);
+        let rays_traced = AtomicU64::new(0);
+        let intersection_tests = AtomicU64::new(0);
+        let framebuffer = Mutex::new(vec![RGB::zero(); self.image_width * self.image_height]);
+        let tile_area = options.tile_size * options.tile_size;
+
+        thread::scope(|scope| {
+            for _ in 0..options.num_threads.min(tiles.len()) {
+                scope.spawn(|| {
+                    let mut pixels = vec![RGB::zero(); tile_area];
+                    while !control.cancel.is_cancelled() {
+                        let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                        let Some(tile) = tiles.get(index) else {
+                            break;
+                        };
+
+                        let pixels = &mut pixels[..tile.pixel_count()];
+                        shade_tile(index, tile, pixels);
+
+                        {
+                            let mut framebuffer = framebuffer.lock().expect("Framebuffer poisoned");
+                            for (row, y) in (tile.start_y..tile.end_y).enumerate() {
+                                let offset = y * self.image_width + tile.start_x;
+                                framebuffer[offset..offset + tile.width()].copy_from_slice(
+                                    &pixels[row * tile.width()..(row + 1) * tile.width()],
+                                );
+                            }
+                        }
+
+                        let done = completed_tiles.fetch_add(1, Ordering::Relaxed) + 1;
+                        let fraction = done as f64 / tiles.len() as f64;
+                        control
+                            .observer
+                            .tile_completed(tile, progress.0 + progress.1 * fraction);
+                    }
+
+                    let (rays, tests) = stats::take_counters();
+                    rays_traced.fetch_add(rays, Ordering::Relaxed);
+                    intersection_tests.fetch_add(tests, Ordering::Relaxed);
+                });
+            }
+        });
+
+        stats.add_counters((rays_traced.into_inner(), intersection_tests.into_inner()));
+        let completed = completed_tiles.into_inner() == tiles.len();
+        (
+            framebuffer.into_inner().expect("Framebuffer poisoned"),
+            completed,
+        )
     }
 
-    fn render_row(&self, world: Arc<Hittable>, y: usize) -> Vec<RGB> {
+    fn render_row(&self, world: Arc<Hittable>, lighting: &Lighting, y: usize) -> Vec<RGB> {
         let mut row = Vec::<RGB>::new();
         for x in 0..self.image_width {
             let world = world.clone();
-            row.push(self.render_pixel(world, y, x));
+            row.push(self.render_pixel(world, lighting, y, x));
         }
 
         row
     }
 
-    fn render_pixel(&self, world: Arc<Hittable>, y: usize, x: usize) -> RGB {
+    fn render_pixel(&self, world: Arc<Hittable>, lighting: &Lighting, y: usize, x: usize) -> RGB {
+        self.sample_pixel(world, lighting, y, x, self.samples_per_pixel) * self.pixel_sample_scale
+    }
+
+    // Sum of `samples` radiance samples through pixel (x, y).
+    fn sample_pixel(
+        &self,
+        world: Arc<Hittable>,
+        lighting: &Lighting,
+        y: usize,
+        x: usize,
+        samples: usize,
+    ) -> RGB {
         let mut color = RGB::new([0.0, 0.0, 0.0]);
-        for _ in 0..self.samples_per_pixel {
-            let ray = self.get_ray(x, y);
+        let weight = self.lens.as_ref().map_or(1.0, |lens| lens.weight());
+        for _ in 0..samples {
+            let mut path = PathState::new(self.spectral);
+            let Some(ray) = self.get_ray(x, y) else {
+                continue;
+            };
+            let ray = ray.with_wavelength(path.hero_wavelength());
             let world = world.clone();
-            color = color + self.ray_color(ray, world, self.max_depth);
+            let radiance = self.ray_color(ray, world, lighting, self.max_depth, &mut path);
+            color = color + path.film(radiance) * weight;
         }
 
-        color * self.pixel_sample_scale
+        color
     }
 
     pub fn rgb_array_to_ppm(&self, image: Vec<RGB>) -> String {
@@ -235,3 +553,119 @@ impl Camera {
         ppm_data
     }
 }
+
+// Per-sample state that follows a path through `ray_color`.
+struct PathState {
+    media: MediumStack,
+    wavelengths: Option<SampledWavelengths>,
+    bsdf_pdf: Option<f64>, // density the current ray was sampled with, if not specular
+    normal: Vector<3>,     // surface normal where the current ray started
+}
+
+impl PathState {
+    fn new(spectral: bool) -> Self {
+        Self {
+            media: MediumStack::new(),
+            wavelengths: spectral.then(|| SampledWavelengths::sample_visible(fastrand::f64())),
+            bsdf_pdf: None,
+            normal: Vector::zero(),
+        }
+    }
+
+    fn hero_wavelength(&self) -> Option<f64> {
+        self.wavelengths.map(|wavelengths| wavelengths.hero())
+    }
+
+    // Converts the radiance the path carried to the film's RGB.
+    fn film(&self, radiance: RGB) -> RGB {
+        match self.wavelengths {
+            Some(wavelengths) => wavelengths.to_rgb(radiance),
+            None => radiance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtx::{sample_scene, Scene, TileOrder};
+
+    const SEED: u64 = 7;
+
+    // The sample scene at a size that renders in a moment.
+    fn small_scene() -> Scene {
+        let scene = sample_scene::get();
+        let camera = &scene.camera;
+        Scene {
+            camera: Camera::new(
+                24,
+                16,
+                4,
+                5,
+                camera.vfov,
+                camera.lookfrom,
+                camera.lookat,
+                camera.vup,
+                camera.defocus_angle,
+                camera.focus_dist,
+            ),
+            ..scene
+        }
+    }
+
+    fn render_st(seed: u64) -> Vec<RGB> {
+        fastrand::seed(seed);
+        small_scene().render_st(&RenderControl::default()).image
+    }
+
+    fn render_mt(seed: u64, num_threads: usize) -> Vec<RGB> {
+        let mut options = TileOptions::new(4, num_threads, TileOrder::Scanline);
+        options.seed = Some(seed);
+        small_scene()
+            .render_mt(&options, &RenderControl::default())
+            .image
+    }
+
+    #[test]
+    fn same_seed_renders_match_on_one_thread() {
+        assert_eq!(render_st(SEED), render_st(SEED));
+        assert_ne!(render_st(SEED), render_st(SEED + 1));
+    }
+
+    #[test]
+    fn same_seed_renders_match_on_many_threads() {
+        assert_eq!(render_mt(SEED, 4), render_mt(SEED, 4));
+        assert_eq!(render_mt(SEED, 1), render_mt(SEED, 4));
+        assert_ne!(render_mt(SEED, 4), render_mt(SEED + 1, 4));
+    }
+
+    fn render_progressive(
+        samples_per_pixel: usize,
+        options: &CheckpointOptions,
+    ) -> Result<Vec<RGB>, CheckpointError> {
+        let mut scene = small_scene();
+        scene.camera.samples_per_pixel = samples_per_pixel;
+        let tile_options = TileOptions::new(4, 2, TileOrder::Scanline);
+        Ok(scene
+            .render_progressive(&tile_options, options, &RenderControl::default())?
+            .image)
+    }
+
+    #[test]
+    fn resumed_renders_can_take_more_samples() {
+        let path = std::env::temp_dir().join(format!("rtrace-refine-{}.ckpt", std::process::id()));
+        let mut options = CheckpointOptions::new(&path);
+        options.samples_per_pass = 2;
+        options.seed = SEED;
+
+        render_progressive(4, &options).unwrap();
+        let refined = render_progressive(8, &options);
+        let fewer = render_progressive(6, &options);
+        options.resume = false;
+        let uninterrupted = render_progressive(8, &options);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(refined.unwrap(), uninterrupted.unwrap());
+        assert!(matches!(fewer, Err(CheckpointError::TooManySamples(8))));
+    }
+}
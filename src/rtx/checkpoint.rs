@@ -0,0 +1,425 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::RGB;
+
+const MAGIC: &[u8; 4] = b"RTCK";
+const VERSION: u32 = 2;
+const HEADER_BYTES: u64 = 8 + 7 * 8; // magic, version and seven u64 fields
+const PIXEL_BYTES: u64 = 4 + 3 * 8; // sample count and accumulated RGB
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Format(String),
+    SceneChanged,
+    CameraChanged,
+    SamplesPerPassChanged(usize), // what the checkpoint was written with
+    TooManySamples(usize),        // samples per pixel already accumulated
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "checkpoint I/O error: {}", err),
+            CheckpointError::Format(msg) => write!(f, "invalid checkpoint file: {}", msg),
+            CheckpointError::SceneChanged => {
+                write!(f, "scene has changed since the checkpoint was written")
+            }
+            CheckpointError::CameraChanged => {
+                write!(f, "camera has changed since the checkpoint was written")
+            }
+            CheckpointError::SamplesPerPassChanged(samples) => write!(
+                f,
+                "checkpoint was written with {} samples per pass",
+                samples
+            ),
+            CheckpointError::TooManySamples(samples) => write!(
+                f,
+                "checkpoint already holds {} samples per pixel, more than requested",
+                samples
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckpointOptions {
+    pub path: PathBuf,           // where the checkpoint is written
+    pub samples_per_pass: usize, // samples added to every pixel per pass
+    pub interval: Duration,      // minimum time between checkpoint writes
+    pub seed: u64,               // base seed for the per pass RNG streams
+    pub resume: bool,            // continue from `path` if it exists
+}
+
+impl CheckpointOptions {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            samples_per_pass: 10,
+            interval: Duration::from_secs(60),
+            seed: 0,
+            resume: true,
+        }
+    }
+}
+
+/// Snapshot of a progressive render. The RNG state is fully described by
+/// `seed` and `passes_completed`: every pass reseeds its tiles from those.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub scene_hash: u64,
+    pub camera_hash: u64,
+    pub seed: u64,
+    pub samples_per_pass: usize,
+    pub passes_completed: usize,
+    pub sample_counts: Vec<u32>,
+    pub accumulation: Vec<RGB>,
+}
+
+impl Checkpoint {
+    pub fn new(
+        image_width: usize,
+        image_height: usize,
+        scene_hash: u64,
+        camera_hash: u64,
+        seed: u64,
+        samples_per_pass: usize,
+    ) -> Self {
+        let pixels = image_width * image_height;
+        Self {
+            image_width,
+            image_height,
+            scene_hash,
+            camera_hash,
+            seed,
+            samples_per_pass,
+            passes_completed: 0,
+            sample_counts: vec![0; pixels],
+            accumulation: vec![RGB::zero(); pixels],
+        }
+    }
+
+    /// Passes of a different size would not line up with the ones already
+    /// accumulated, so `samples_per_pass` has to match as well. The target
+    /// `samples_per_pixel` may grow between runs but not drop below what
+    /// has been accumulated.
+    pub fn validate(
+        &self,
+        scene_hash: u64,
+        camera_hash: u64,
+        samples_per_pass: usize,
+        samples_per_pixel: usize,
+    ) -> Result<(), CheckpointError> {
+        if self.camera_hash != camera_hash {
+            return Err(CheckpointError::CameraChanged);
+        }
+        if self.scene_hash != scene_hash {
+            return Err(CheckpointError::SceneChanged);
+        }
+        if self.samples_per_pass != samples_per_pass {
+            return Err(CheckpointError::SamplesPerPassChanged(
+                self.samples_per_pass,
+            ));
+        }
+        if self.samples_completed() > samples_per_pixel {
+            return Err(CheckpointError::TooManySamples(self.samples_completed()));
+        }
+        Ok(())
+    }
+
+    /// Samples every pixel has received so far. Passes cover the whole
+    /// image, so the count is the same for all of them.
+    pub fn samples_completed(&self) -> usize {
+        self.sample_counts.first().copied().unwrap_or(0) as usize
+    }
+
+    /// Averaged image of everything accumulated so far.
+    pub fn image(&self) -> Vec<RGB> {
+        self.accumulation
+            .iter()
+            .zip(self.sample_counts.iter())
+            .map(|(&sum, &count)| {
+                if count == 0 {
+                    RGB::zero()
+                } else {
+                    sum / count as f64
+                }
+            })
+            .collect()
+    }
+
+    /// Writes to a temporary file first so a crash mid-write never
+    /// clobbers the previous checkpoint.
+    pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp_path)?);
+            out.write_all(MAGIC)?;
+            out.write_all(&VERSION.to_le_bytes())?;
+            for value in [
+                self.image_width as u64,
+                self.image_height as u64,
+                self.scene_hash,
+                self.camera_hash,
+                self.seed,
+                self.samples_per_pass as u64,
+                self.passes_completed as u64,
+            ] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+            for count in &self.sample_counts {
+                out.write_all(&count.to_le_bytes())?;
+            }
+            for pixel in &self.accumulation {
+                for component in pixel.components {
+                    out.write_all(&component.to_le_bytes())?;
+                }
+            }
+            out.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, CheckpointError> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(CheckpointError::Format("bad magic".to_string()));
+        }
+        let version = read_u32(&mut input)?;
+        if version != VERSION {
+            return Err(CheckpointError::Format(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+
+        let image_width = read_u64(&mut input)? as usize;
+        let image_height = read_u64(&mut input)? as usize;
+        let scene_hash = read_u64(&mut input)?;
+        let camera_hash = read_u64(&mut input)?;
+        let seed = read_u64(&mut input)?;
+        let samples_per_pass = read_u64(&mut input)? as usize;
+        let passes_completed = read_u64(&mut input)? as usize;
+
+        // The header is untrusted: check it against the data that follows
+        // before allocating anything from it.
+        let pixels = (image_width as u64)
+            .checked_mul(image_height as u64)
+            .filter(|&pixels| pixels > 0)
+            .ok_or_else(|| {
+                CheckpointError::Format(format!(
+                    "invalid image size {}x{}",
+                    image_width, image_height
+                ))
+            })?;
+        if pixels.checked_mul(PIXEL_BYTES) != file_len.checked_sub(HEADER_BYTES) {
+            return Err(CheckpointError::Format(format!(
+                "{}x{} image does not match the file length",
+                image_width, image_height
+            )));
+        }
+        let pixels = pixels as usize;
+        let mut sample_counts = Vec::with_capacity(pixels);
+        for _ in 0..pixels {
+            sample_counts.push(read_u32(&mut input)?);
+        }
+        let mut accumulation = Vec::with_capacity(pixels);
+        for _ in 0..pixels {
+            accumulation.push(RGB::new([
+                read_f64(&mut input)?,
+                read_f64(&mut input)?,
+                read_f64(&mut input)?,
+            ]));
+        }
+
+        Ok(Self {
+            image_width,
+            image_height,
+            scene_hash,
+            camera_hash,
+            seed,
+            samples_per_pass,
+            passes_completed,
+            sample_counts,
+            accumulation,
+        })
+    }
+}
+
+/// Stable FNV-1a hash of a value's debug representation, streamed rather
+/// than collected into a string. Types holding bulk data (images and the
+/// tables derived from them) keep their debug output compact, so this stays
+/// cheap for large scenes.
+pub fn fingerprint<T: fmt::Debug>(value: &T) -> u64 {
+    let mut hasher = Fnv1a::new();
+    fmt::write(&mut hasher, format_args!("{:?}", value)).expect("hashing cannot fail");
+    hasher.finish()
+}
+
+/// FNV-1a hasher. Unlike `DefaultHasher` it does not change between
+/// compiler releases, so checkpoints stay valid across rebuilds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fnv1a(u64);
+
+impl Fnv1a {
+    pub fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+/// Seed for one tile of one pass, derived with splitmix64 so neighbouring
+/// tiles and passes get uncorrelated streams.
+pub fn pass_seed(seed: u64, pass: usize, tile: usize) -> u64 {
+    let mut z = seed
+        .wrapping_add((pass as u64).wrapping_mul(0x9e3779b97f4a7c15))
+        .wrapping_add((tile as u64).wrapping_mul(0xbf58476d1ce4e5b9));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(input: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SEED: u64 = 42;
+    const SAMPLES_PER_PASS: usize = 10;
+
+    // A checkpoint of a 3x2 image after one pass, with distinct values in
+    // every pixel so a misordered read would show.
+    fn sample_checkpoint() -> Checkpoint {
+        let mut checkpoint = Checkpoint::new(3, 2, 0x5ce4e, 0xca3e4a, SEED, SAMPLES_PER_PASS);
+        for (i, pixel) in checkpoint.accumulation.iter_mut().enumerate() {
+            *pixel = RGB::new([i as f64, 0.5 * i as f64, -1.0]);
+        }
+        checkpoint.sample_counts = vec![SAMPLES_PER_PASS as u32; 6];
+        checkpoint.passes_completed = 1;
+        checkpoint
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rtrace-{}-{}.ckpt", name, std::process::id()))
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let path = temp_path("round-trip");
+        let checkpoint = sample_checkpoint();
+        checkpoint.save(&path).unwrap();
+        let loaded = Checkpoint::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, checkpoint);
+    }
+
+    #[test]
+    fn validates_matching_settings() {
+        let checkpoint = sample_checkpoint();
+        assert!(checkpoint
+            .validate(0x5ce4e, 0xca3e4a, SAMPLES_PER_PASS, SAMPLES_PER_PASS)
+            .is_ok());
+        assert!(checkpoint
+            .validate(0x5ce4e, 0xca3e4a, SAMPLES_PER_PASS, 1000)
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_mismatched_resume() {
+        let checkpoint = sample_checkpoint();
+        assert!(matches!(
+            checkpoint.validate(0x5ce4e, 0xca3e4a, SAMPLES_PER_PASS + 1, 1000),
+            Err(CheckpointError::SamplesPerPassChanged(SAMPLES_PER_PASS))
+        ));
+        assert!(matches!(
+            checkpoint.validate(0x5ce4e, 0, SAMPLES_PER_PASS, 1000),
+            Err(CheckpointError::CameraChanged)
+        ));
+        assert!(matches!(
+            checkpoint.validate(0, 0xca3e4a, SAMPLES_PER_PASS, 1000),
+            Err(CheckpointError::SceneChanged)
+        ));
+        assert!(matches!(
+            checkpoint.validate(0x5ce4e, 0xca3e4a, SAMPLES_PER_PASS, SAMPLES_PER_PASS - 1),
+            Err(CheckpointError::TooManySamples(SAMPLES_PER_PASS))
+        ));
+    }
+
+    #[test]
+    fn rejects_sizes_the_file_cannot_hold() {
+        let path = temp_path("oversized");
+        sample_checkpoint().save(&path).unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let oversized = Checkpoint::load(&path);
+
+        bytes.truncate(bytes.len() - 1);
+        bytes[8..16].copy_from_slice(&3u64.to_le_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let truncated = Checkpoint::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(oversized, Err(CheckpointError::Format(_))));
+        assert!(matches!(truncated, Err(CheckpointError::Format(_))));
+    }
+}
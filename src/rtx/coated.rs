@@ -0,0 +1,111 @@
+use super::conductor::MicrofacetReflection;
+use super::microfacet::TrowbridgeReitz;
+use super::{fresnel, Hit, Material, Medium, Ray, ScatterResult, Vector, RGB};
+
+// A dielectric clear coat over another material, such as varnished wood
+// or car paint. The coat reflects by its Fresnel term and the base sees
+// what the coat transmits on the way in and out:
+//
+//   f = f_coat + (1 - F(wo)) (1 - F(wi)) f_base
+//
+// Refraction inside the coat is ignored, so the base is evaluated with
+// the outside directions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoatedData {
+    pub base: Material,
+    pub refraction_index: f64,
+    pub roughness: f64,
+}
+
+impl CoatedData {
+    pub fn new(base: Material, refraction_index: f64, roughness: f64) -> Self {
+        Self {
+            base,
+            refraction_index,
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    fn coat(&self) -> MicrofacetReflection {
+        MicrofacetReflection::new(TrowbridgeReitz::from_roughness(self.roughness))
+    }
+
+    fn fresnel(&self, cos_theta: f64) -> f64 {
+        fresnel::dielectric(cos_theta, self.refraction_index)
+    }
+
+    // Probability of sampling the coat rather than the base.
+    fn coat_probability(&self, incoming: Ray, hit: &Hit) -> f64 {
+        self.fresnel(cos_theta(incoming.direction * -1.0, hit))
+    }
+
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let coat_probability = self.coat_probability(incoming, hit);
+
+        if fastrand::f64() < coat_probability {
+            // The coat reflects F of the light and is picked with probability F.
+            let scatter = self
+                .coat()
+                .scatter(incoming, hit, |_| RGB::new([1.0, 1.0, 1.0]))?;
+            if scatter.specular {
+                return Some(scatter);
+            }
+            return self.mixture(incoming, hit, scatter.scattered);
+        }
+
+        let scatter = self.base.scatter(incoming, hit)?;
+        if scatter.specular {
+            // (1 - F(wo)) cancels against the probability of reaching the base.
+            let transmitted = 1.0 - self.fresnel(cos_theta(scatter.scattered.direction, hit));
+            return Some(ScatterResult::specular(
+                incoming,
+                scatter.attenuation * transmitted,
+                scatter.scattered,
+            ));
+        }
+        self.mixture(incoming, hit, scatter.scattered)
+    }
+
+    // Weights a non-specular sample by the full layered BSDF and pdf.
+    fn mixture(&self, incoming: Ray, hit: &Hit, scattered: Ray) -> Option<ScatterResult> {
+        let direction = scattered.direction;
+        let pdf = self.pdf(incoming, hit, direction);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = self.eval(incoming, hit, direction) / pdf;
+        Some(ScatterResult::new(incoming, attenuation, scattered, pdf))
+    }
+
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let coat = self.coat().eval(incoming, hit, direction, |cos| {
+            let reflectance = self.fresnel(cos);
+            RGB::new([reflectance, reflectance, reflectance])
+        });
+        let transmitted = (1.0 - self.fresnel(cos_theta(incoming.direction * -1.0, hit)))
+            * (1.0 - self.fresnel(cos_theta(direction, hit)));
+        coat + self.base.eval(incoming, hit, direction) * transmitted
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        let coat_probability = self.coat_probability(incoming, hit);
+        coat_probability * self.coat().pdf(incoming, hit, direction)
+            + (1.0 - coat_probability) * self.base.pdf(incoming, hit, direction)
+    }
+
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        self.base.emitted(hit)
+    }
+
+    pub fn medium(&self) -> Option<Medium> {
+        self.base.medium()
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.base.is_dispersive()
+    }
+}
+
+fn cos_theta(direction: Vector<3>, hit: &Hit) -> f64 {
+    hit.normal.dot(direction.normalize()).abs()
+}
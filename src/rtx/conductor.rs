@@ -0,0 +1,220 @@
+use super::microfacet::{same_hemisphere, TrowbridgeReitz};
+use super::{fresnel, Hit, Onb, Ray, ScatterResult, ThinFilm, Vector, RGB};
+
+// Rough metal: GGX microfacet reflection with the exact Fresnel term for a
+// complex index of refraction eta + i k.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConductorData {
+    pub eta: RGB,
+    pub k: RGB,
+    pub roughness: f64,
+    pub film: Option<ThinFilm>, // oxide or coating layer, e.g. heat tinted steel
+}
+
+impl ConductorData {
+    pub fn new(eta: RGB, k: RGB, roughness: f64) -> Self {
+        Self {
+            eta,
+            k,
+            roughness: roughness.clamp(0.0, 1.0),
+            film: None,
+        }
+    }
+
+    pub fn with_film(self, film: ThinFilm) -> Self {
+        Self {
+            film: Some(film),
+            ..self
+        }
+    }
+
+    // Measured optical constants fitted to linear sRGB primaries.
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(
+            RGB::new([0.18299, 0.42108, 1.37340]),
+            RGB::new([3.42420, 2.34590, 1.77040]),
+            roughness,
+        )
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(
+            RGB::new([0.15943, 0.14512, 0.13547]),
+            RGB::new([3.92910, 3.19000, 2.38080]),
+            roughness,
+        )
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(
+            RGB::new([0.27105, 0.67693, 1.31640]),
+            RGB::new([3.60920, 2.62480, 2.29210]),
+            roughness,
+        )
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::new(
+            RGB::new([1.65740, 0.88036, 0.52123]),
+            RGB::new([9.22380, 6.26950, 4.83700]),
+            roughness,
+        )
+    }
+
+    pub fn iron(roughness: f64) -> Self {
+        Self::new(
+            RGB::new([2.91140, 2.94970, 2.58450]),
+            RGB::new([3.08930, 2.93180, 2.76700]),
+            roughness,
+        )
+    }
+
+    pub fn preset(name: &str, roughness: f64) -> Option<Self> {
+        match name {
+            "gold" => Some(Self::gold(roughness)),
+            "silver" => Some(Self::silver(roughness)),
+            "copper" => Some(Self::copper(roughness)),
+            "aluminium" | "aluminum" => Some(Self::aluminium(roughness)),
+            "iron" => Some(Self::iron(roughness)),
+            _ => None,
+        }
+    }
+
+    fn reflection(&self) -> MicrofacetReflection {
+        MicrofacetReflection::new(TrowbridgeReitz::from_roughness(self.roughness))
+    }
+
+    fn fresnel(&self, cos_theta: f64) -> RGB {
+        match self.film {
+            Some(film) => film.reflectance(cos_theta, 1.0, self.eta, self.k),
+            None => fresnel::conductor(cos_theta, self.eta, self.k),
+        }
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        self.reflection()
+            .scatter(incoming, hit, |cos| self.fresnel(cos))
+    }
+
+    pub fn eval(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        self.reflection()
+            .eval(incoming, hit, direction, |cos| self.fresnel(cos))
+    }
+
+    pub fn pdf(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        self.reflection().pdf(incoming, hit, direction)
+    }
+}
+
+// Microfacet reflection lobe shared by the metal-like materials, which only
+// differ in their Fresnel term.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MicrofacetReflection {
+    pub distribution: TrowbridgeReitz,
+}
+
+impl MicrofacetReflection {
+    pub fn new(distribution: TrowbridgeReitz) -> Self {
+        Self { distribution }
+    }
+
+    pub fn scatter(
+        &self,
+        incoming: Ray,
+        hit: &Hit,
+        fresnel: impl Fn(f64) -> RGB,
+    ) -> Option<ScatterResult> {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        if wo.components[2] <= 0.0 {
+            return None;
+        }
+
+        if self.distribution.effectively_smooth() {
+            let [x, y, z] = wo.components;
+            let wi = Vector::new([-x, -y, z]);
+            let outgoing = Ray::new(hit.p, onb.to_world(wi));
+            return Some(ScatterResult::specular(incoming, fresnel(z), outgoing));
+        }
+
+        let wi = self.sample_local(wo)?;
+        let pdf = self.pdf_local(wo, wi);
+        let attenuation = self.eval_local(wo, wi, &fresnel) / pdf;
+        let outgoing = Ray::new(hit.p, onb.to_world(wi));
+        Some(ScatterResult::new(incoming, attenuation, outgoing, pdf))
+    }
+
+    // BSDF times the cosine of the incident direction.
+    pub fn eval(
+        &self,
+        incoming: Ray,
+        hit: &Hit,
+        direction: Vector<3>,
+        fresnel: impl Fn(f64) -> RGB,
+    ) -> RGB {
+        if self.distribution.effectively_smooth() {
+            return RGB::zero();
+        }
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        self.eval_local(wo, wi, &fresnel)
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        if self.distribution.effectively_smooth() {
+            return 0.0;
+        }
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        self.pdf_local(wo, wi)
+    }
+
+    // The methods below work in the local shading frame of the rough lobe.
+
+    pub fn sample_local(&self, wo: Vector<3>) -> Option<Vector<3>> {
+        let wm = self.distribution.sample_wm(wo);
+        let wi = wo.reflect(wm) * -1.0;
+        same_hemisphere(wo, wi).then_some(wi)
+    }
+
+    pub fn pdf_local(&self, wo: Vector<3>, wi: Vector<3>) -> f64 {
+        if !same_hemisphere(wo, wi) {
+            return 0.0;
+        }
+        let Some(wm) = half_vector(wo, wi) else {
+            return 0.0;
+        };
+        self.distribution.pdf(wo, wm) / (4.0 * wo.dot(wm).abs())
+    }
+
+    // BSDF times cos theta_i.
+    pub fn eval_local(&self, wo: Vector<3>, wi: Vector<3>, fresnel: &impl Fn(f64) -> RGB) -> RGB {
+        let cos_theta_o = wo.components[2];
+        let cos_theta_i = wi.components[2];
+        if cos_theta_o <= 0.0 || cos_theta_i <= 0.0 {
+            return RGB::zero();
+        }
+        let Some(wm) = half_vector(wo, wi) else {
+            return RGB::zero();
+        };
+        let d = self.distribution.d(wm);
+        let g = self.distribution.g(wo, wi);
+        fresnel(wo.dot(wm).abs()) * (d * g / (4.0 * cos_theta_o))
+    }
+}
+
+// Microfacet normal halfway between two directions, oriented along +z.
+fn half_vector(wo: Vector<3>, wi: Vector<3>) -> Option<Vector<3>> {
+    let wm = wo + wi;
+    if wm.length_squared() == 0.0 {
+        return None;
+    }
+    let wm = wm.normalize();
+    Some(if wm.components[2] < 0.0 {
+        -1.0 * wm
+    } else {
+        wm
+    })
+}
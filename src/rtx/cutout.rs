@@ -0,0 +1,63 @@
+use super::{Hit, Material, Medium, Ray, ScatterResult, Texture, Vector, RGB};
+
+// Opacity mask over another material, for leaf cards, fences and decals.
+// Hits where `alpha` falls below `threshold` are skipped by the geometry.
+// With `stochastic` set, the remaining hits are kept with probability
+// alpha, so fractional alpha averages out to partial transparency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CutoutData {
+    pub base: Material,
+    pub alpha: Texture,
+    pub threshold: f64,
+    pub stochastic: bool,
+}
+
+impl CutoutData {
+    pub fn new(base: Material, alpha: Texture, threshold: f64) -> Self {
+        Self {
+            base,
+            alpha,
+            threshold,
+            stochastic: false,
+        }
+    }
+
+    pub fn stochastic(self) -> Self {
+        Self {
+            stochastic: true,
+            ..self
+        }
+    }
+
+    pub fn is_opaque(&self, hit: &Hit) -> bool {
+        let alpha = self.alpha.scalar(hit.u, hit.v, hit.p);
+        if alpha < self.threshold {
+            return false;
+        }
+        !self.stochastic || alpha >= 1.0 || fastrand::f64() < alpha
+    }
+
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        self.base.scatter(incoming, hit)
+    }
+
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        self.base.eval(incoming, hit, direction)
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        self.base.pdf(incoming, hit, direction)
+    }
+
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        self.base.emitted(hit)
+    }
+
+    pub fn medium(&self) -> Option<Medium> {
+        self.base.medium()
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.base.is_dispersive()
+    }
+}
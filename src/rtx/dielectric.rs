@@ -1,39 +1,145 @@
-use super::{Hit, Ray, ScatterResult, RGB};
+use super::spectrum::{Dispersion, LAMBDA_D};
+use super::{fresnel, Hit, Medium, Ray, ScatterResult, ThinFilm, Vector, RGB};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DielectricData {
     pub refraction_index: f64,
+    pub absorption: RGB, // Beer-Lambert coefficient of the interior
+    pub dispersion: Option<Dispersion>, // overrides refraction_index in spectral renders
+    pub film: Option<ThinFilm>, // coating on the outside of the surface
 }
 
 impl DielectricData {
     pub fn new(refraction_index: f64) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            absorption: RGB::zero(),
+            dispersion: None,
+            film: None,
+        }
     }
 
-    pub fn scatter(self, incoming: Ray, hit: Hit) -> ScatterResult {
+    // A dispersive glass; `refraction_index` becomes its d-line index, which
+    // is what RGB renders use.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            dispersion: Some(dispersion),
+            ..Self::new(dispersion.refraction_index(LAMBDA_D))
+        }
+    }
+
+    pub fn with_absorption(self, absorption: RGB) -> Self {
+        Self { absorption, ..self }
+    }
+
+    pub fn with_film(self, film: ThinFilm) -> Self {
+        Self {
+            film: Some(film),
+            ..self
+        }
+    }
+
+    // The interior that rays refracted into this object travel through.
+    pub fn medium(self) -> Option<Medium> {
+        (self.absorption != RGB::zero()).then(|| Medium::new(self.absorption))
+    }
+
+    pub fn is_dispersive(self) -> bool {
+        self.dispersion.is_some()
+    }
+
+    pub fn refraction_index_at(self, wavelength: Option<f64>) -> f64 {
+        match (self.dispersion, wavelength) {
+            (Some(dispersion), Some(lambda)) => dispersion.refraction_index(lambda),
+            _ => self.refraction_index,
+        }
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        if let Some(film) = self.film {
+            return self.scatter_film(film, incoming, hit);
+        }
         let attenuation = RGB::new([1.0, 1.0, 1.0]);
+        let refraction_index = self.refraction_index_at(incoming.wavelength);
         let ri = if hit.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let unit_direction = incoming.direction.normalize();
         let cos_theta = (-1.0 * unit_direction).dot(hit.normal).min(1.0);
-        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
 
-        let cannot_refract = ri * sin_theta > 1.0;
-        let direction = if cannot_refract || self.reflectance(cos_theta) > fastrand::f64() {
-            unit_direction.reflect(hit.normal)
+        // Total internal reflection shows up as a reflectance of one.
+        let direction =
+            if self.reflectance(cos_theta, hit.front_face, incoming.wavelength) > fastrand::f64() {
+                unit_direction.reflect(hit.normal)
+            } else {
+                unit_direction.refract(hit.normal, ri)
+            };
+
+        Some(ScatterResult::specular(
+            incoming,
+            attenuation,
+            Ray::new(hit.p, direction),
+        ))
+    }
+
+    // With a film the reflectance is coloured, so reflection is picked by
+    // its average and the colour goes into the attenuation.
+    fn scatter_film(self, film: ThinFilm, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let white = RGB::new([1.0, 1.0, 1.0]);
+        let refraction_index = self.refraction_index_at(incoming.wavelength);
+        let (outside, inside) = if hit.front_face {
+            (1.0, refraction_index)
+        } else {
+            (refraction_index, 1.0)
+        };
+
+        let unit_direction = incoming.direction.normalize();
+        let cos_theta = (-1.0 * unit_direction).dot(hit.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let cannot_refract = outside / inside * sin_theta > 1.0;
+        let reflectance = film.reflectance(cos_theta, outside, inside * white, RGB::zero());
+        let probability = reflectance.components.iter().sum::<f64>() / 3.0;
+
+        let (direction, attenuation) = if cannot_refract {
+            (unit_direction.reflect(hit.normal), white)
+        } else if probability > fastrand::f64() {
+            (
+                unit_direction.reflect(hit.normal),
+                reflectance / probability,
+            )
         } else {
-            unit_direction.refract(hit.normal, ri)
+            (
+                unit_direction.refract(hit.normal, outside / inside),
+                (white - reflectance) / (1.0 - probability),
+            )
         };
 
-        return ScatterResult::new(incoming, hit, attenuation, Ray::new(hit.p, direction));
+        Some(ScatterResult::specular(
+            incoming,
+            attenuation,
+            Ray::new(hit.p, direction),
+        ))
     }
 
-    fn reflectance(self, cosine: f64) -> f64 {
-        let r0 = ((1.0 - self.refraction_index) / (1.0 + self.refraction_index)).powf(2.0);
-        r0 + (1.0 - r0) * (1.0 - cosine).powf(5.0)
+    // Perfectly smooth, so light sampling never finds a contributing direction.
+    pub fn eval(self, _incoming: Ray, _hit: &Hit, _direction: Vector<3>) -> RGB {
+        RGB::zero()
+    }
+
+    pub fn pdf(self, _incoming: Ray, _hit: &Hit, _direction: Vector<3>) -> f64 {
+        0.0
+    }
+
+    pub fn reflectance(self, cosine: f64, front_face: bool, wavelength: Option<f64>) -> f64 {
+        let refraction_index = self.refraction_index_at(wavelength);
+        let eta = if front_face {
+            refraction_index
+        } else {
+            1.0 / refraction_index
+        };
+        fresnel::dielectric(cosine, eta)
     }
 }
@@ -0,0 +1,100 @@
+// Piecewise constant distributions for importance sampling tabulated
+// functions such as environment maps. Samples are continuous in [0, 1)
+// and densities are with respect to that domain.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution1D {
+    function: Vec<f64>,
+    cdf: Vec<f64>, // function.len() + 1 entries, from 0 to 1
+    integral: f64,
+}
+
+impl Distribution1D {
+    // An all zero function is sampled uniformly.
+    pub fn new(function: Vec<f64>) -> Self {
+        let n = function.len();
+        let mut cdf = vec![0.0; n + 1];
+        for i in 0..n {
+            cdf[i + 1] = cdf[i] + function[i].abs() / n as f64;
+        }
+        let integral = cdf[n];
+        for (i, value) in cdf.iter_mut().enumerate() {
+            *value = if integral > 0.0 {
+                *value / integral
+            } else {
+                i as f64 / n as f64
+            };
+        }
+        Self {
+            function,
+            cdf,
+            integral,
+        }
+    }
+
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    // Returns the sample, its density and the segment it fell into.
+    pub fn sample(&self, u: f64) -> (f64, f64, usize) {
+        let n = self.function.len();
+        let offset = self.cdf.partition_point(|&c| c <= u).clamp(1, n) - 1;
+        let width = self.cdf[offset + 1] - self.cdf[offset];
+        let du = if width > 0.0 {
+            (u - self.cdf[offset]) / width
+        } else {
+            0.5
+        };
+        let x = ((offset as f64 + du) / n as f64).min(1.0 - f64::EPSILON);
+        (x, self.pdf(offset), offset)
+    }
+
+    pub fn pdf(&self, offset: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.function[offset].abs() / self.integral
+        } else {
+            1.0
+        }
+    }
+
+    pub fn offset(&self, x: f64) -> usize {
+        ((x * self.function.len() as f64) as usize).min(self.function.len() - 1)
+    }
+}
+
+// Distribution over [0, 1)^2 from a row major grid: a marginal picks the
+// row, then that row's conditional picks the column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    pub fn new(function: &[f64], width: usize, height: usize) -> Self {
+        let conditional: Vec<Distribution1D> = function
+            .chunks_exact(width)
+            .take(height)
+            .map(|row| Distribution1D::new(row.to_vec()))
+            .collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|row| row.integral()).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    // Returns (u, v) with u along rows and v down the columns, and its density.
+    pub fn sample(&self, u: f64, v: f64) -> ((f64, f64), f64) {
+        let (y, pdf_y, row) = self.marginal.sample(v);
+        let (x, pdf_x, _) = self.conditional[row].sample(u);
+        ((x, y), pdf_x * pdf_y)
+    }
+
+    pub fn pdf(&self, u: f64, v: f64) -> f64 {
+        let row = self.marginal.offset(v);
+        let conditional = &self.conditional[row];
+        self.marginal.pdf(row) * conditional.pdf(conditional.offset(u))
+    }
+}
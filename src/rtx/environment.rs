@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use super::distribution::Distribution2D;
+use super::{Vector, RGB};
+use crate::image::Image;
+
+// Radiance arriving from infinitely far away in every direction, stored as
+// an equirectangular image: u runs once round the horizon starting at +z,
+// so the image centre faces -z, and v from straight up (top row) to
+// straight down. Texels are importance sampled by luminance times the
+// solid angle they cover.
+#[derive(Clone, PartialEq)]
+pub struct EnvironmentMap {
+    pub image: Arc<Image>,
+    pub rotation: f64, // radians about +y
+    pub intensity: f64,
+    distribution: Distribution2D,
+}
+
+// The sampling tables follow from the image, so they are left out.
+impl fmt::Debug for EnvironmentMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnvironmentMap")
+            .field("image", &self.image)
+            .field("rotation", &self.rotation)
+            .field("intensity", &self.intensity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EnvironmentMap {
+    pub fn new(image: Arc<Image>, rotation: f64, intensity: f64) -> Self {
+        let (width, height) = (image.width, image.height);
+        let weights: Vec<f64> = image
+            .pixels
+            .iter()
+            .enumerate()
+            .map(|(i, pixel)| {
+                let theta = PI * ((i / width) as f64 + 0.5) / height as f64;
+                luminance(*pixel).max(0.0) * theta.sin()
+            })
+            .collect();
+        let distribution = Distribution2D::new(&weights, width, height);
+        Self {
+            image,
+            rotation,
+            intensity,
+            distribution,
+        }
+    }
+
+    pub fn radiance(&self, direction: Vector<3>) -> RGB {
+        let (u, v) = self.uv(direction);
+        self.lookup(u, v)
+    }
+
+    // A direction towards the map, the radiance from there and its solid
+    // angle density.
+    pub fn sample(&self) -> Option<(Vector<3>, RGB, f64)> {
+        let ((u, v), map_pdf) = self.distribution.sample(fastrand::f64(), fastrand::f64());
+        let theta = v * PI;
+        let sin_theta = theta.sin();
+        if map_pdf == 0.0 || sin_theta == 0.0 {
+            return None;
+        }
+        let pdf = map_pdf / (2.0 * PI * PI * sin_theta);
+        Some((self.direction(u, v), self.lookup(u, v), pdf))
+    }
+
+    pub fn pdf(&self, direction: Vector<3>) -> f64 {
+        let (u, v) = self.uv(direction);
+        let sin_theta = (v * PI).sin();
+        if sin_theta == 0.0 {
+            return 0.0;
+        }
+        self.distribution.pdf(u, v) / (2.0 * PI * PI * sin_theta)
+    }
+
+    fn lookup(&self, u: f64, v: f64) -> RGB {
+        let x = (u * self.image.width as f64) as usize;
+        let y = (v * self.image.height as f64) as usize;
+        self.image.pixel(x, y) * self.intensity
+    }
+
+    fn uv(&self, direction: Vector<3>) -> (f64, f64) {
+        let [x, y, z] = direction.normalize().components;
+        let phi = x.atan2(-z) - self.rotation;
+        let u = (0.5 + phi / (2.0 * PI)).rem_euclid(1.0);
+        let v = y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn direction(&self, u: f64, v: f64) -> Vector<3> {
+        let phi = (u - 0.5) * 2.0 * PI + self.rotation;
+        let theta = v * PI;
+        Vector::new([
+            theta.sin() * phi.sin(),
+            theta.cos(),
+            -theta.sin() * phi.cos(),
+        ])
+    }
+}
+
+fn luminance(color: RGB) -> f64 {
+    let [r, g, b] = color.components;
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
@@ -0,0 +1,224 @@
+use std::ops::{Add, Div, Mul, Sub};
+
+use super::spectrum::rgb_matching;
+use super::RGB;
+
+const FILM_SAMPLES: usize = 16; // wavelengths the film reflectance is averaged over
+const FILM_LAMBDA_MIN: f64 = 380.0; // nm
+const FILM_LAMBDA_MAX: f64 = 780.0; // nm
+
+// Fresnel reflectance of a conductor with complex index of refraction
+// eta + i k, evaluated per colour channel.
+pub fn conductor(cos_theta_i: f64, eta: RGB, k: RGB) -> RGB {
+    RGB::new(std::array::from_fn(|i| {
+        conductor_channel(
+            cos_theta_i,
+            Complex::new(eta.components[i], k.components[i]),
+        )
+    }))
+}
+
+// Unpolarized Fresnel reflectance at a boundary between two dielectrics.
+// `eta` is the relative index n_t / n_i; a negative cosine means the ray
+// arrives from the other side. Returns 1 under total internal reflection.
+pub fn dielectric(cos_theta_i: f64, eta: f64) -> f64 {
+    let mut cos_theta_i = cos_theta_i.clamp(-1.0, 1.0);
+    let mut eta = eta;
+    if cos_theta_i < 0.0 {
+        eta = 1.0 / eta;
+        cos_theta_i = -cos_theta_i;
+    }
+
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+
+    let r_parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    (r_parallel * r_parallel + r_perpendicular * r_perpendicular) / 2.0
+}
+
+pub fn schlick(cos_theta_i: f64, r0: RGB) -> RGB {
+    let weight = (1.0 - cos_theta_i.clamp(0.0, 1.0)).powi(5);
+    r0 + weight * (RGB::new([1.0, 1.0, 1.0]) - r0)
+}
+
+// A thin transparent layer on top of a surface, as on soap bubbles, oil
+// slicks and coated lenses. Light reflected at its two boundaries
+// interferes, so reflectance depends on wavelength.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinFilm {
+    pub thickness: f64,        // nm
+    pub refraction_index: f64, // absolute index of the film
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, refraction_index: f64) -> Self {
+        Self {
+            thickness: thickness.max(0.0),
+            refraction_index,
+        }
+    }
+
+    // Reflectance of the film on a substrate with complex index eta + i k,
+    // seen from a medium of index `outside`; all indices are absolute and
+    // the substrate's are fitted per colour channel. The spectral
+    // reflectance of the Airy sum is projected back to RGB so that a flat
+    // spectrum keeps its value.
+    pub fn reflectance(&self, cos_theta_i: f64, outside: f64, eta: RGB, k: RGB) -> RGB {
+        let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+        let mut rgb = RGB::zero();
+        let mut white = RGB::zero();
+        for i in 0..FILM_SAMPLES {
+            let t = (i as f64 + 0.5) / FILM_SAMPLES as f64;
+            let lambda = FILM_LAMBDA_MIN + t * (FILM_LAMBDA_MAX - FILM_LAMBDA_MIN);
+            let substrate = Complex::new(channel_at(eta, lambda), channel_at(k, lambda));
+            let matching = rgb_matching(lambda);
+            rgb = rgb + matching * self.spectral(cos_theta_i, outside, substrate, lambda);
+            white = white + matching;
+        }
+        RGB::new(std::array::from_fn(|c| {
+            (rgb.components[c] / white.components[c]).clamp(0.0, 1.0)
+        }))
+    }
+
+    fn spectral(&self, cos_theta_i: f64, outside: f64, substrate: Complex, lambda: f64) -> f64 {
+        let n1 = Complex::real(outside);
+        let n2 = Complex::real(self.refraction_index);
+        let sin_theta = outside * (1.0 - cos_theta_i * cos_theta_i).sqrt();
+        let cos_in = |n: Complex| {
+            let ratio = Complex::real(sin_theta) / n;
+            (Complex::real(1.0) - ratio * ratio).sqrt()
+        };
+        let (c1, c2, c3) = (Complex::real(cos_theta_i), cos_in(n2), cos_in(substrate));
+
+        // Round trip phase through the film; complex when the wave is
+        // evanescent, which damps the second reflection.
+        let delta = Complex::real(4.0 * std::f64::consts::PI * self.thickness / lambda) * n2 * c2;
+        let phase = Complex::new(0.0, 1.0) * delta;
+        let phase = Complex::new(
+            phase.re.exp() * phase.im.cos(),
+            phase.re.exp() * phase.im.sin(),
+        );
+
+        let airy = |r12: Complex, r23: Complex| {
+            let r = (r12 + r23 * phase) / (Complex::real(1.0) + r12 * r23 * phase);
+            r.norm()
+        };
+        let s = airy(
+            (n1 * c1 - n2 * c2) / (n1 * c1 + n2 * c2),
+            (n2 * c2 - substrate * c3) / (n2 * c2 + substrate * c3),
+        );
+        let p = airy(
+            (n2 * c1 - n1 * c2) / (n2 * c1 + n1 * c2),
+            (substrate * c2 - n2 * c3) / (substrate * c2 + n2 * c3),
+        );
+        ((s + p) / 2.0).clamp(0.0, 1.0)
+    }
+}
+
+// Reads an RGB fitted quantity at a wavelength by interpolating between
+// the channels' dominant wavelengths.
+fn channel_at(color: RGB, lambda: f64) -> f64 {
+    let [r, g, b] = color.components;
+    let (blue, green, red) = (465.0, 550.0, 610.0);
+    if lambda <= blue {
+        b
+    } else if lambda <= green {
+        b + (g - b) * (lambda - blue) / (green - blue)
+    } else if lambda <= red {
+        g + (r - g) * (lambda - green) / (red - green)
+    } else {
+        r
+    }
+}
+
+fn conductor_channel(cos_theta_i: f64, eta: Complex) -> f64 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+    let sin2_theta_t = Complex::real(sin2_theta_i) / (eta * eta);
+    let cos_theta_t = (Complex::real(1.0) - sin2_theta_t).sqrt();
+
+    let cos_i = Complex::real(cos_theta_i);
+    let r_parallel = (eta * cos_i - cos_theta_t) / (eta * cos_i + cos_theta_t);
+    let r_perpendicular = (cos_i - eta * cos_theta_t) / (cos_i + eta * cos_theta_t);
+    (r_parallel.norm() + r_perpendicular.norm()) / 2.0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    fn real(re: f64) -> Self {
+        Self::new(re, 0.0)
+    }
+
+    // Squared magnitude.
+    fn norm(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // Principal square root.
+    fn sqrt(self) -> Self {
+        let n = self.norm().sqrt();
+        if n == 0.0 {
+            return Self::real(0.0);
+        }
+        let t1 = (0.5 * (n + self.re.abs())).sqrt();
+        let t2 = 0.5 * self.im / t1;
+        if self.re >= 0.0 {
+            Self::new(t1, t2)
+        } else {
+            Self::new(t2.abs(), t1.copysign(self.im))
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let scale = 1.0 / other.norm();
+        Self::new(
+            scale * (self.re * other.re + self.im * other.im),
+            scale * (self.im * other.re - self.re * other.im),
+        )
+    }
+}
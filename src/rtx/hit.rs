@@ -1,23 +1,30 @@
-use super::{Material, Ray, Target, Vector};
+use super::{Material, Ray, SphereData, Vector};
 
+// Borrows the surface it lies on, so building one for every candidate
+// intersection costs no clones.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Hit {
+pub struct Hit<'a> {
     pub p: Vector<3>,
     pub normal: Vector<3>,
     pub t: f64,
+    pub u: f64, // surface coordinates for texture lookups
+    pub v: f64,
+    pub dpdu: Vector<3>, // surface tangents along u and v, zero if unknown
+    pub dpdv: Vector<3>,
     pub front_face: bool,
-    pub target: Target,
-    pub material: Material,
+    pub sphere: &'a SphereData,
+    pub material: &'a Material,
 }
 
-impl Hit {
+impl<'a> Hit<'a> {
     pub fn new(
         p: Vector<3>,
         t: f64,
+        (u, v): (f64, f64),
         ray: Ray,
         outward_normal: Vector<3>,
-        target: Target,
-        material: Material,
+        sphere: &'a SphereData,
+        material: &'a Material,
     ) -> Self {
         let front_face = ray.direction.dot(outward_normal) < 0.0;
         let normal = if front_face {
@@ -30,9 +37,26 @@ impl Hit {
             p,
             normal,
             t,
+            u,
+            v,
+            dpdu: Vector::zero(),
+            dpdv: Vector::zero(),
             front_face,
-            target,
+            sphere,
             material,
         }
     }
+
+    pub fn with_derivatives(self, dpdu: Vector<3>, dpdv: Vector<3>) -> Self {
+        Self { dpdu, dpdv, ..self }
+    }
+
+    // The geometric normal on the outside of the surface.
+    pub fn outward_normal(&self) -> Vector<3> {
+        if self.front_face {
+            self.normal
+        } else {
+            -1.0 * self.normal
+        }
+    }
 }
@@ -10,11 +10,11 @@ pub enum Hittable {
 }
 
 pub trait HittableObject {
-    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit>;
+    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit<'_>>;
 }
 
 impl HittableObject for Hittable {
-    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit> {
+    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit<'_>> {
         match self {
             Hittable::Single(target) => target.hit(ray, t),
             Hittable::Multiple(list) => list.hit(ray, t),
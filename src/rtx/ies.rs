@@ -0,0 +1,338 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::{Onb, Vector};
+
+// Angular intensity distribution of a real fixture from an IES LM-63 file.
+// Only type C photometry is read, the kind used for architectural
+// fixtures: vertical angles run from the nadir (0) to the zenith (180) and
+// horizontal angles turn about the vertical axis. Candela values are
+// scaled so the brightest direction is 1, leaving the light's intensity
+// as the fixture's peak; `peak` keeps the absolute scale for lights that
+// want the file's own photometry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IesProfile {
+    vertical: Vec<f64>,   // degrees, increasing
+    horizontal: Vec<f64>, // degrees, increasing
+    candela: Vec<f64>,    // one row of vertical values per horizontal angle
+    peak: f64,            // candela in the brightest direction, multiplier applied
+}
+
+impl IesProfile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut lines = text.lines();
+        let tilt = lines
+            .by_ref()
+            .map(str::trim)
+            .find(|line| line.starts_with("TILT="))
+            .ok_or_else(|| invalid_data("missing TILT line".to_string()))?;
+
+        let mut numbers = lines
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|token| !token.is_empty())
+            .map(|token| {
+                token
+                    .parse::<f64>()
+                    .map_err(|_| invalid_data(format!("invalid number '{}'", token)))
+            });
+        let mut next = move || {
+            numbers
+                .next()
+                .unwrap_or_else(|| Err(invalid_data("unexpected end of file".to_string())))
+        };
+
+        // Tilt data describes how the lamp's output changes with the
+        // fixture's inclination, which does not apply to a fixed light.
+        if tilt == "TILT=INCLUDE" {
+            next()?; // lamp to luminaire geometry
+            let pairs = next()? as usize;
+            for _ in 0..2 * pairs {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens = next()?;
+        let multiplier = next()?;
+        let vertical_count = next()? as usize;
+        let horizontal_count = next()? as usize;
+        let photometric_type = next()?;
+        for _ in 0..7 {
+            next()?; // units, dimensions, ballast factors and input watts
+        }
+        if photometric_type != 1.0 {
+            return Err(invalid_data(format!(
+                "unsupported photometric type {}, only type C is read",
+                photometric_type
+            )));
+        }
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(invalid_data("profile has no angles".to_string()));
+        }
+
+        let vertical = (0..vertical_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<_>>>()?;
+        let horizontal = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<io::Result<Vec<_>>>()?;
+        let candela = (0..vertical_count * horizontal_count)
+            .map(|_| next().map(|value| value * multiplier))
+            .collect::<io::Result<Vec<_>>>()?;
+        if !vertical.windows(2).all(|w| w[0] < w[1]) || !horizontal.windows(2).all(|w| w[0] < w[1])
+        {
+            return Err(invalid_data("angles must increase".to_string()));
+        }
+        // LM-63 only defines horizontal sets from 0 (full, quadrant and
+        // bilateral symmetry) or from 90 to 270 (bilateral about that plane).
+        let (first, last) = (horizontal[0], horizontal[horizontal_count - 1]);
+        if first != 0.0 && (first, last) != (90.0, 270.0) {
+            return Err(invalid_data(format!(
+                "unsupported horizontal angles {} to {}",
+                first, last
+            )));
+        }
+
+        let max = candela.iter().copied().fold(0.0, f64::max);
+        if max <= 0.0 {
+            return Err(invalid_data("profile emits no light".to_string()));
+        }
+        Ok(Self {
+            vertical,
+            horizontal,
+            candela: candela.into_iter().map(|value| value / max).collect(),
+            peak: max,
+        })
+    }
+
+    pub fn peak(&self) -> f64 {
+        self.peak
+    }
+
+    // Relative intensity at a vertical angle from the nadir and a
+    // horizontal angle, both in degrees, interpolated between the table's
+    // angles. Profiles covering only part of the horizontal circle are
+    // mirrored round it by their symmetry.
+    pub fn intensity(&self, vertical: f64, horizontal: f64) -> f64 {
+        let first = self.horizontal[0];
+        let last = self.horizontal[self.horizontal.len() - 1];
+        let mut h = horizontal.rem_euclid(360.0);
+        if first == 90.0 {
+            // Symmetric about the 90-270 plane: the 0 side mirrors onto 180.
+            if !(90.0..=270.0).contains(&h) {
+                h = (180.0 - h).rem_euclid(360.0);
+            }
+        } else if last <= 180.0 && h > 180.0 {
+            h = 360.0 - h;
+        }
+        if last <= 90.0 && h > 90.0 {
+            h = 180.0 - h;
+        }
+
+        let (h0, h1, th) = bracket(&self.horizontal, h);
+        let Some((v0, v1, tv)) = within(&self.vertical, vertical) else {
+            return 0.0;
+        };
+        let row = |h: usize| {
+            let values = &self.candela[h * self.vertical.len()..];
+            (1.0 - tv) * values[v0] + tv * values[v1]
+        };
+        (1.0 - th) * row(h0) + th * row(h1)
+    }
+
+    // Relative intensity emitted along `direction` by a fixture whose
+    // nadir points along `axis`. Horizontal angle zero faces +x, or +z for
+    // fixtures aimed along x.
+    pub fn towards(&self, axis: Vector<3>, direction: Vector<3>) -> f64 {
+        let w = axis.normalize();
+        let reference = if w.components[0].abs() > 0.9 {
+            Vector::new([0.0, 0.0, 1.0])
+        } else {
+            Vector::new([1.0, 0.0, 0.0])
+        };
+        let u = (reference - w * reference.dot(w)).normalize();
+        let frame = Onb {
+            u,
+            v: w.cross(u),
+            w,
+        };
+        let local = frame.to_local(direction.normalize());
+        let [x, y, z] = local.components;
+        let vertical = z.clamp(-1.0, 1.0).acos().to_degrees();
+        let horizontal = y.atan2(x).rem_euclid(2.0 * PI).to_degrees();
+        self.intensity(vertical, horizontal)
+    }
+}
+
+// Indices of the angles around `x` and how far it lies between them, or
+// `None` outside the table.
+fn within(angles: &[f64], x: f64) -> Option<(usize, usize, f64)> {
+    let (first, last) = (angles[0], angles[angles.len() - 1]);
+    if x < first - 1e-9 || x > last + 1e-9 {
+        return None;
+    }
+    Some(bracket(angles, x))
+}
+
+// Like `within`, but clamping to the ends of the table.
+fn bracket(angles: &[f64], x: f64) -> (usize, usize, f64) {
+    let upper = angles.partition_point(|&angle| angle <= x);
+    if upper == 0 {
+        return (0, 0, 0.0);
+    }
+    if upper == angles.len() {
+        let last = angles.len() - 1;
+        return (last, last, 0.0);
+    }
+    let lower = upper - 1;
+    let t = (x - angles[lower]) / (angles[upper] - angles[lower]);
+    (lower, upper, t)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A downlight with the same output at every horizontal angle, its beam
+    // fading from 1000 cd at the nadir to nothing at 90 degrees.
+    const DOWNLIGHT: &str = "IESNA:LM-63-2002
+[TEST] downlight
+[MANUFAC] sample
+TILT=NONE
+1 1000 1 4 1 1 2 0.1 0.1 0
+1 1 20
+0 30 60 90
+0
+1000 800 200 0
+";
+
+    // A wall washer symmetric about its 0-180 degree plane, throwing
+    // twice as much light towards 0 as towards 90 and none behind.
+    const WALL_WASHER: &str = "IESNA:LM-63-1995
+TILT=NONE
+1, 2000, 2.0, 3, 3, 1, 2, 0.3, 0.1, 0.0
+1.0 1.0 35
+0 45 90
+0 90 180
+100 80 0
+50 40 0
+0 0 0
+";
+
+    // A fixture symmetric about its 90-270 degree plane, the half of the
+    // horizontal circle its table covers. Brightest towards 90, fading to a
+    // fifth of that towards 270.
+    const SIDE_WASHER: &str = "IESNA:LM-63-2002
+TILT=NONE
+1 1000 1 3 3 1 2 0.2 0.2 0
+1 1 30
+0 45 90
+90 180 270
+100 50 0
+60 30 0
+20 10 0
+";
+
+    #[test]
+    fn parses_angles_and_normalizes_candela() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        assert_eq!(profile.vertical, vec![0.0, 30.0, 60.0, 90.0]);
+        assert_eq!(profile.horizontal, vec![0.0]);
+        assert_eq!(profile.candela, vec![1.0, 0.8, 0.2, 0.0]);
+        assert_eq!(profile.peak(), 1000.0);
+        assert_eq!(IesProfile::parse(WALL_WASHER).unwrap().peak(), 200.0);
+    }
+
+    #[test]
+    fn interpolates_vertical_angles() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        assert!((profile.intensity(0.0, 0.0) - 1.0).abs() < 1e-12);
+        assert!((profile.intensity(15.0, 0.0) - 0.9).abs() < 1e-12);
+        assert!((profile.intensity(45.0, 0.0) - 0.5).abs() < 1e-12);
+        assert_eq!(profile.intensity(120.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn rotationally_symmetric_profiles_ignore_horizontal_angle() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        for horizontal in [0.0, 45.0, 135.0, 270.0, 359.0] {
+            assert!((profile.intensity(30.0, horizontal) - 0.8).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn bilateral_profiles_mirror_across_the_zero_plane() {
+        let profile = IesProfile::parse(WALL_WASHER).unwrap();
+        assert_eq!(profile.intensity(0.0, 0.0), 1.0);
+        assert!((profile.intensity(0.0, 90.0) - 0.5).abs() < 1e-12);
+        assert!((profile.intensity(0.0, 45.0) - 0.75).abs() < 1e-12);
+        assert_eq!(
+            profile.intensity(45.0, 300.0),
+            profile.intensity(45.0, 60.0)
+        );
+        assert_eq!(profile.intensity(0.0, 180.0), 0.0);
+    }
+
+    #[test]
+    fn bilateral_profiles_mirror_across_the_ninety_plane() {
+        let profile = IesProfile::parse(SIDE_WASHER).unwrap();
+        assert_eq!(profile.intensity(0.0, 90.0), 1.0);
+        assert!((profile.intensity(0.0, 0.0) - 0.6).abs() < 1e-12);
+        assert!((profile.intensity(0.0, 45.0) - 0.8).abs() < 1e-12);
+        assert!((profile.intensity(0.0, 315.0) - 0.4).abs() < 1e-12);
+        assert_eq!(
+            profile.intensity(45.0, 30.0),
+            profile.intensity(45.0, 150.0)
+        );
+        assert_eq!(
+            profile.intensity(45.0, 330.0),
+            profile.intensity(45.0, 210.0)
+        );
+    }
+
+    #[test]
+    fn nadir_follows_the_axis() {
+        let profile = IesProfile::parse(DOWNLIGHT).unwrap();
+        let down = Vector::new([0.0, -1.0, 0.0]);
+        assert!((profile.towards(down, down) - 1.0).abs() < 1e-12);
+        assert!(profile.towards(down, Vector::new([1.0, 0.0, 0.0])).abs() < 1e-9);
+    }
+
+    #[test]
+    fn horizontal_zero_faces_x() {
+        let profile = IesProfile::parse(WALL_WASHER).unwrap();
+        let down = Vector::new([0.0, -1.0, 0.0]);
+        let tilted = |x: f64, z: f64| Vector::new([x, -1.0, z]);
+        assert!((profile.towards(down, tilted(1.0, 0.0)) - 0.8).abs() < 1e-9);
+        assert!((profile.towards(down, tilted(0.0, 1.0)) - 0.4).abs() < 1e-9);
+        assert!(profile.towards(down, tilted(-1.0, 0.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn skips_included_tilt_data() {
+        let text = DOWNLIGHT.replace("TILT=NONE", "TILT=INCLUDE\n1\n2\n0 90\n1 1");
+        assert_eq!(
+            IesProfile::parse(&text).unwrap(),
+            IesProfile::parse(DOWNLIGHT).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_profiles() {
+        assert!(IesProfile::parse("IESNA:LM-63-2002\n").is_err());
+        assert!(IesProfile::parse(&DOWNLIGHT.replace("1000 800", "1000 x")).is_err());
+        assert!(IesProfile::parse(&DOWNLIGHT.replace("0.1 0.1 0\n", "0.1 0.1 0\n3 ")).is_err());
+        assert!(IesProfile::parse(&DOWNLIGHT.replace("1 1 2 0.1", "1 2 2 0.1")).is_err());
+        assert!(IesProfile::parse(&SIDE_WASHER.replace("90 180 270", "45 180 270")).is_err());
+    }
+}
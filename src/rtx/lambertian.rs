@@ -1,3 +1,5 @@
+use std::f64::consts::PI;
+
 use super::{Hit, Ray, ScatterResult, Vector, RGB};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -10,7 +12,7 @@ impl LambertianData {
         Self { albedo }
     }
 
-    pub fn scatter(self, incoming: Ray, hit: Hit) -> ScatterResult {
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
         let scattered = hit.normal + Vector::random_unit_vector();
         let outgoing = Ray::new(
             hit.p,
@@ -20,7 +22,18 @@ impl LambertianData {
                 scattered
             },
         );
+        let pdf = self.pdf(incoming, hit, outgoing.direction);
+
+        Some(ScatterResult::new(incoming, self.albedo, outgoing, pdf))
+    }
+
+    pub fn eval(self, _incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let cosine = hit.normal.dot(direction.normalize()).max(0.0);
+        self.albedo * (cosine / PI)
+    }
 
-        return ScatterResult::new(incoming, hit, self.albedo, outgoing);
+    // Cosine weighted hemisphere sampling.
+    pub fn pdf(self, _incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        hit.normal.dot(direction.normalize()).max(0.0) / PI
     }
 }
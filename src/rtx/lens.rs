@@ -0,0 +1,276 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::Vector;
+
+const MM: f64 = 0.001; // scene units per millimetre; scenes are taken to be in metres
+
+// A camera lens described surface by surface, traced like the realistic
+// camera of Kolb, Mitchell and Hanrahan (1995). Rays start on the film,
+// pass through a point on the rear element and refract through every
+// surface, so aberrations, vignetting and the shape of out of focus
+// highlights come from the design itself.
+//
+// Tables list surfaces from the front of the lens to the back, one per
+// line: curvature radius, thickness to the next surface (the last one to
+// the film), index of refraction behind the surface and aperture
+// diameter, all lengths in millimetres. A zero radius marks the aperture
+// stop; an index of zero means air. Anything after `#` is ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LensSystem {
+    elements: Vec<LensElement>,
+    pub film_diagonal: f64, // mm
+    exposure: f64,          // makes the centre of the image as bright as a pinhole's
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LensElement {
+    radius: f64,    // mm, positive when the centre of curvature is towards the film
+    thickness: f64, // mm to the next surface towards the film
+    eta: f64,       // of the medium between this surface and the next
+    aperture: f64,  // radius in mm
+}
+
+impl LensSystem {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut elements = Vec::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values = line
+                .split_whitespace()
+                .map(|token| {
+                    token
+                        .parse::<f64>()
+                        .map_err(|_| invalid_data(format!("invalid number '{}'", token)))
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let [radius, thickness, eta, diameter]: [f64; 4] =
+                values.try_into().map_err(|values: Vec<f64>| {
+                    invalid_data(format!(
+                        "lens surfaces have 4 values, found {}",
+                        values.len()
+                    ))
+                })?;
+            elements.push(LensElement {
+                radius,
+                thickness,
+                eta: if eta == 0.0 { 1.0 } else { eta },
+                aperture: diameter / 2.0,
+            });
+        }
+        if elements.is_empty() {
+            return Err(invalid_data("lens table has no surfaces".to_string()));
+        }
+        Ok(Self {
+            elements,
+            film_diagonal: 35.0,
+            exposure: 1.0,
+        })
+    }
+
+    // Stops the aperture down to `diameter` mm, if narrower than the
+    // design's.
+    pub fn with_aperture(mut self, diameter: f64) -> Self {
+        for element in &mut self.elements {
+            if element.radius == 0.0 {
+                element.aperture = element.aperture.min(diameter / 2.0);
+            }
+        }
+        self
+    }
+
+    pub fn with_film_diagonal(mut self, diameter: f64) -> Self {
+        self.film_diagonal = diameter;
+        self
+    }
+
+    // The lens with its film moved so that objects `distance` scene units
+    // in front of it are sharp.
+    pub fn focused(&self, distance: f64) -> Self {
+        let mut lens = self.clone();
+        lens.focus(distance / MM);
+        lens.exposure = lens.exposure();
+        lens
+    }
+
+    // A ray towards the scene from the point (s, t) of an image with the
+    // given aspect ratio, each from 0 at the top left to 1 at the bottom
+    // right. The lens flips the image, so the film is read rotated half a
+    // turn. Origin and direction are in the camera's frame (x right, y up,
+    // looking down -z) with the film at the origin. `None` if the lens
+    // blocks the ray.
+    pub fn ray(&self, s: f64, t: f64, aspect_ratio: f64) -> Option<(Vector<3>, Vector<3>)> {
+        let film_height = self.film_diagonal / (1.0 + aspect_ratio * aspect_ratio).sqrt();
+        let film = Vector::new([
+            (0.5 - s) * film_height * aspect_ratio,
+            (t - 0.5) * film_height,
+            0.0,
+        ]);
+        let rear = self.elements[self.elements.len() - 1];
+        let disk = Vector::<3>::random_in_unit_disk();
+        let target = Vector::new([
+            disk.components[0] * rear.aperture,
+            disk.components[1] * rear.aperture,
+            -rear.thickness,
+        ]);
+        let (origin, direction) = self.trace(film, (target - film).normalize(), true)?;
+        Some((origin * MM, direction))
+    }
+
+    // Scale for radiance through the lens, see `exposure`.
+    pub fn weight(&self) -> f64 {
+        self.exposure
+    }
+
+    // Refracts a ray through every surface in turn, towards the scene from
+    // the film or the other way. `None` if it misses a surface, is stopped
+    // by an aperture or is totally internally reflected.
+    fn trace(
+        &self,
+        mut origin: Vector<3>,
+        mut direction: Vector<3>,
+        towards_scene: bool,
+    ) -> Option<(Vector<3>, Vector<3>)> {
+        let n = self.elements.len();
+        let mut z = vec![0.0; n];
+        let mut position = 0.0;
+        for i in (0..n).rev() {
+            position -= self.elements[i].thickness;
+            z[i] = position;
+        }
+
+        let order: Box<dyn Iterator<Item = usize>> = if towards_scene {
+            Box::new((0..n).rev())
+        } else {
+            Box::new(0..n)
+        };
+        for i in order {
+            let element = self.elements[i];
+            let (t, normal) = if element.radius == 0.0 {
+                if direction.components[2] == 0.0 {
+                    return None;
+                }
+                let t = (z[i] - origin.components[2]) / direction.components[2];
+                (t, Vector::new([0.0, 0.0, 1.0]))
+            } else {
+                let center = Vector::new([0.0, 0.0, z[i] + element.radius]);
+                let t = sphere(origin, direction, center, element.radius)?;
+                (t, (origin + t * direction - center).normalize())
+            };
+            if t <= 0.0 {
+                return None;
+            }
+            origin = origin + t * direction;
+            let [x, y, _] = origin.components;
+            if x * x + y * y > element.aperture * element.aperture {
+                return None;
+            }
+
+            if element.radius != 0.0 {
+                let normal = if normal.dot(direction) > 0.0 {
+                    -1.0 * normal
+                } else {
+                    normal
+                };
+                let film_side = element.eta;
+                let scene_side = if i > 0 { self.elements[i - 1].eta } else { 1.0 };
+                let eta = if towards_scene {
+                    film_side / scene_side
+                } else {
+                    scene_side / film_side
+                };
+                direction = refract(direction, normal, eta)?;
+            }
+        }
+        Some((origin, direction))
+    }
+
+    // Sets the distance from the rear surface to the film so that a point
+    // on the axis `distance` mm in front of the lens is imaged onto it,
+    // found by tracing a ray just off the axis from that point.
+    fn focus(&mut self, distance: f64) {
+        let front_z: f64 = -self.elements.iter().map(|e| e.thickness).sum::<f64>();
+        let height = 0.05 * self.elements[0].aperture;
+        let origin = Vector::new([0.0, 0.0, front_z - distance]);
+        let target = Vector::new([height, 0.0, front_z]);
+        let Some((o, d)) = self.trace(origin, (target - origin).normalize(), false) else {
+            return;
+        };
+        if d.components[0] >= 0.0 {
+            return; // the lens cannot focus this close
+        }
+        let image_z = o.components[2] - o.components[0] / d.components[0] * d.components[2];
+        let last = self.elements.len() - 1;
+        let thickness = self.elements[last].thickness + image_z;
+        if thickness > 0.0 {
+            self.elements[last].thickness = thickness;
+        }
+    }
+
+    // Rays through the rear element are wasted when the lens blocks them,
+    // which darkens the image. This is one over the share that get through
+    // from the centre of the film.
+    fn exposure(&self) -> f64 {
+        let rear = self.elements[self.elements.len() - 1];
+        let (mut total, mut passed) = (0, 0);
+        for i in 0..64 {
+            for j in 0..64 {
+                let x = (2.0 * (i as f64 + 0.5) / 64.0 - 1.0) * rear.aperture;
+                let y = (2.0 * (j as f64 + 0.5) / 64.0 - 1.0) * rear.aperture;
+                if x * x + y * y > rear.aperture * rear.aperture {
+                    continue;
+                }
+                total += 1;
+                let direction = Vector::new([x, y, -rear.thickness]).normalize();
+                if self.trace(Vector::zero(), direction, true).is_some() {
+                    passed += 1;
+                }
+            }
+        }
+        if passed == 0 {
+            return 1.0;
+        }
+        total as f64 / passed as f64
+    }
+}
+
+// Distance along the ray to the surface of a sphere centred on the axis
+// that faces the way the lens tables describe it: the cap nearer the scene
+// for positive radii.
+fn sphere(origin: Vector<3>, direction: Vector<3>, center: Vector<3>, radius: f64) -> Option<f64> {
+    let oc = origin - center;
+    let b = oc.dot(direction);
+    let c = oc.length_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let (t0, t1) = (-b - root, -b + root);
+    let closer = (direction.components[2] > 0.0) ^ (radius < 0.0);
+    Some(if closer { t0 } else { t1 })
+}
+
+// Snell's law for a unit direction and a normal facing against it, `None`
+// on total internal reflection.
+fn refract(direction: Vector<3>, normal: Vector<3>, eta: f64) -> Option<Vector<3>> {
+    let cos_i = -direction.dot(normal);
+    let sin2_t = eta * eta * (1.0 - cos_i * cos_i).max(0.0);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    Some((eta * direction + (eta * cos_i - cos_t) * normal).normalize())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
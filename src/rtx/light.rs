@@ -0,0 +1,365 @@
+use std::sync::Arc;
+
+use super::{
+    stats, EnvironmentMap, Hit, Hittable, HittableObject, IesProfile, Interval, LightBvh,
+    PhysicalSky, Ray, Vector, RGB,
+};
+
+// What rays leaving the scene see.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Background {
+    #[default]
+    Gradient, // white at the horizon to light blue overhead
+    Environment(Arc<EnvironmentMap>),
+    Sky(Arc<PhysicalSky>),
+}
+
+impl Background {
+    pub fn radiance(&self, direction: Vector<3>) -> RGB {
+        match self {
+            Background::Gradient => {
+                let t = 0.5 * (direction.normalize().components[1] + 1.0);
+                (1.0 - t) * RGB::new([1.0, 1.0, 1.0]) + t * RGB::new([0.5, 0.7, 1.0])
+            }
+            Background::Environment(map) => map.radiance(direction),
+            Background::Sky(sky) => sky.radiance(direction),
+        }
+    }
+
+    // A direction to light shading points from, with its radiance and
+    // solid angle density, for backgrounds that can be importance sampled.
+    pub fn sample(&self) -> Option<(Vector<3>, RGB, f64)> {
+        match self {
+            Background::Gradient => None,
+            Background::Environment(map) => map.sample(),
+            Background::Sky(sky) => sky.sample(),
+        }
+    }
+
+    pub fn pdf(&self, direction: Vector<3>) -> f64 {
+        match self {
+            Background::Gradient => 0.0,
+            Background::Environment(map) => map.pdf(direction),
+            Background::Sky(sky) => sky.pdf(direction),
+        }
+    }
+}
+
+// The lights of a scene. Emissive surfaces are lit by whatever rays find
+// them, and also sampled directly once `add_emitters` has indexed them.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Lighting {
+    pub lights: Vec<Light>,
+    pub background: Background,
+    pub emitters: Option<Arc<LightBvh>>,
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    // Indexes the emissive spheres in `world` for next-event estimation.
+    pub fn add_emitters(&mut self, world: &Hittable) {
+        self.emitters = LightBvh::new(world).map(Arc::new);
+    }
+
+    // Light arriving at `hit` straight from every light, from one emitter
+    // and from the background, scattered towards the viewer. Each gets one
+    // shadow ray against `world`. Emitter and background samples are
+    // weighted against finding the same directions by BSDF sampling, see
+    // `emitted` and `escaped`.
+    pub fn direct(&self, world: &Hittable, incoming: Ray, hit: &Hit) -> RGB {
+        let mut total = RGB::zero();
+        for light in &self.lights {
+            let Some(sample) = light.sample(hit.p) else {
+                continue;
+            };
+            total = total + self.shadowed(world, incoming, hit, sample, 1.0);
+        }
+
+        if let Some(sample) = self
+            .emitters
+            .as_ref()
+            .and_then(|emitters| emitters.sample(hit.p, hit.normal))
+        {
+            let bsdf_pdf = hit.material.pdf(incoming, hit, sample.direction);
+            let weight = power_heuristic(sample.pdf, bsdf_pdf) / sample.pdf;
+            let sample = LightSample {
+                direction: sample.direction,
+                distance: sample.hit.t,
+                radiance: sample.radiance,
+            };
+            total = total + self.shadowed(world, incoming, hit, sample, weight);
+        }
+
+        if let Some((direction, radiance, pdf)) = self.background.sample() {
+            let bsdf_pdf = hit.material.pdf(incoming, hit, direction);
+            let sample = LightSample {
+                direction,
+                distance: f64::INFINITY,
+                radiance,
+            };
+            let weight = power_heuristic(pdf, bsdf_pdf) / pdf;
+            total = total + self.shadowed(world, incoming, hit, sample, weight);
+        }
+        total
+    }
+
+    // Emission of a surface a ray found, leaving `origin` on a surface
+    // with normal `normal`. `bsdf_pdf` is as for `escaped`.
+    pub fn emitted(
+        &self,
+        hit: &Hit,
+        origin: Vector<3>,
+        normal: Vector<3>,
+        bsdf_pdf: Option<f64>,
+    ) -> RGB {
+        let radiance = hit.material.emitted(hit);
+        match (bsdf_pdf, &self.emitters) {
+            (Some(bsdf_pdf), Some(emitters)) if radiance != RGB::zero() => {
+                let light_pdf = emitters.pdf(origin, normal, hit);
+                if light_pdf > 0.0 {
+                    radiance * power_heuristic(bsdf_pdf, light_pdf)
+                } else {
+                    radiance
+                }
+            }
+            _ => radiance,
+        }
+    }
+
+    // Radiance of the background along a ray that left the scene.
+    // `bsdf_pdf` is the density the last bounce sampled it with, or `None`
+    // after a camera ray or specular bounce, which light sampling cannot
+    // reproduce.
+    pub fn escaped(&self, direction: Vector<3>, bsdf_pdf: Option<f64>) -> RGB {
+        let radiance = self.background.radiance(direction);
+        match bsdf_pdf {
+            Some(bsdf_pdf) => {
+                let light_pdf = self.background.pdf(direction);
+                if light_pdf > 0.0 {
+                    radiance * power_heuristic(bsdf_pdf, light_pdf)
+                } else {
+                    radiance
+                }
+            }
+            None => radiance,
+        }
+    }
+
+    fn shadowed(
+        &self,
+        world: &Hittable,
+        incoming: Ray,
+        hit: &Hit,
+        sample: LightSample,
+        weight: f64,
+    ) -> RGB {
+        let f = hit.material.eval(incoming, hit, sample.direction);
+        if f == RGB::zero() {
+            return RGB::zero();
+        }
+        stats::record_ray();
+        let shadow = Ray::new(hit.p, sample.direction).with_wavelength(incoming.wavelength);
+        if world
+            .hit(shadow, Interval::new(0.001, sample.distance - 0.001))
+            .is_some()
+        {
+            return RGB::zero();
+        }
+        f * sample.radiance * weight
+    }
+}
+
+// Multiple importance sampling weight of a strategy with density `a`
+// against one with density `b`.
+fn power_heuristic(a: f64, b: f64) -> f64 {
+    let (a2, b2) = (a * a, b * b);
+    if a2 + b2 == 0.0 {
+        return 0.0;
+    }
+    a2 / (a2 + b2)
+}
+
+// Lights without geometry. They are only ever reached by shadow rays from
+// shaded points, never by rays scattered off surfaces.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Light {
+    Point(PointLightData),
+    Spot(SpotLightData),
+    Directional(DirectionalLightData),
+}
+
+// Direction towards a light from a shaded point, how far away it is (for
+// the shadow ray) and the radiance it delivers there.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSample {
+    pub direction: Vector<3>,
+    pub distance: f64,
+    pub radiance: RGB,
+}
+
+impl Light {
+    // `None` when the point receives nothing from the light.
+    pub fn sample(&self, p: Vector<3>) -> Option<LightSample> {
+        match self {
+            Light::Point(data) => data.sample(p),
+            Light::Spot(data) => data.sample(p),
+            Light::Directional(data) => data.sample(p),
+        }
+    }
+}
+
+// Light emitted evenly in all directions, falling off with the inverse
+// square of the distance. A photometric profile shapes it like a fixture
+// hanging with its nadir straight down.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointLightData {
+    pub position: Vector<3>,
+    pub intensity: RGB,
+    pub profile: Option<Arc<IesProfile>>,
+}
+
+impl PointLightData {
+    pub fn new(position: Vector<3>, intensity: RGB) -> Self {
+        Self {
+            position,
+            intensity,
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: Arc<IesProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    fn sample(&self, p: Vector<3>) -> Option<LightSample> {
+        let sample = towards(self.position, p, self.intensity)?;
+        shaped(
+            sample,
+            self.profile.as_deref(),
+            Vector::new([0.0, -1.0, 0.0]),
+        )
+    }
+}
+
+// A point light restricted to a cone around `direction`. Intensity is full
+// inside `falloff_start` and eases to zero at `cone_angle`, both measured
+// from the axis. A photometric profile is aimed along the axis and shapes
+// the light inside the cone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotLightData {
+    pub position: Vector<3>,
+    pub direction: Vector<3>,
+    pub intensity: RGB,
+    pub cone_angle: f64,    // radians
+    pub falloff_start: f64, // radians
+    pub profile: Option<Arc<IesProfile>>,
+}
+
+impl SpotLightData {
+    pub fn new(
+        position: Vector<3>,
+        direction: Vector<3>,
+        intensity: RGB,
+        cone_angle: f64,
+        falloff_start: f64,
+    ) -> Self {
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cone_angle,
+            falloff_start: falloff_start.min(cone_angle),
+            profile: None,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: Arc<IesProfile>) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    fn sample(&self, p: Vector<3>) -> Option<LightSample> {
+        let sample = towards(self.position, p, self.intensity)?;
+        let mut sample = shaped(sample, self.profile.as_deref(), self.direction)?;
+        let cos_theta = (-1.0 * sample.direction).dot(self.direction);
+        let scale = smoothstep(self.cone_angle.cos(), self.falloff_start.cos(), cos_theta);
+        if scale <= 0.0 {
+            return None;
+        }
+        sample.radiance = sample.radiance * scale;
+        Some(sample)
+    }
+}
+
+// Parallel light from far away, like the sun. `direction` is where the
+// light travels and `irradiance` what it delivers to a surface facing it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLightData {
+    pub direction: Vector<3>,
+    pub irradiance: RGB,
+}
+
+impl DirectionalLightData {
+    pub fn new(direction: Vector<3>, irradiance: RGB) -> Self {
+        Self {
+            direction: direction.normalize(),
+            irradiance,
+        }
+    }
+
+    fn sample(&self, _p: Vector<3>) -> Option<LightSample> {
+        Some(LightSample {
+            direction: -1.0 * self.direction,
+            distance: f64::INFINITY,
+            radiance: self.irradiance,
+        })
+    }
+}
+
+fn towards(position: Vector<3>, p: Vector<3>, intensity: RGB) -> Option<LightSample> {
+    let offset = position - p;
+    let distance_squared = offset.length_squared();
+    if distance_squared == 0.0 {
+        return None;
+    }
+    let distance = distance_squared.sqrt();
+    Some(LightSample {
+        direction: offset / distance,
+        distance,
+        radiance: intensity / distance_squared,
+    })
+}
+
+// Scales a sample by the profile's intensity in the direction the light
+// leaves towards the shaded point.
+fn shaped(
+    mut sample: LightSample,
+    profile: Option<&IesProfile>,
+    axis: Vector<3>,
+) -> Option<LightSample> {
+    let Some(profile) = profile else {
+        return Some(sample);
+    };
+    let scale = profile.towards(axis, -1.0 * sample.direction);
+    if scale <= 0.0 {
+        return None;
+    }
+    sample.radiance = sample.radiance * scale;
+    Some(sample)
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    if edge0 == edge1 {
+        return if x >= edge1 { 1.0 } else { 0.0 };
+    }
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
@@ -0,0 +1,405 @@
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::fmt;
+
+use super::{Hit, Hittable, HittableObject, Interval, Onb, Ray, SphereData, Target, Vector, RGB};
+
+// Emissive spheres organised for next-event estimation, after the light
+// BVH of pbrt-v4. Every node bounds its lights' positions, total power and
+// emission directions, which give a conservative estimate of how much they
+// can contribute to a shading point. Sampling walks down the tree picking
+// children in proportion to that estimate, so nearby and bright lights are
+// found among thousands at a logarithmic cost.
+#[derive(Clone, PartialEq)]
+pub struct LightBvh {
+    emitters: Vec<SphereData>,
+    nodes: Vec<LightNode>, // depth first; a node's first child follows it
+    trails: BTreeMap<[u64; 4], u64>, // emitter key to the left/right turns reaching it
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LightNode {
+    bounds: LightBounds,
+    kind: NodeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NodeKind {
+    Leaf(usize),     // index into `emitters`
+    Interior(usize), // index of the second child
+}
+
+// One emitter sample: the direction towards the light from the shading
+// point, the light's surface hit in that direction and the density of the
+// whole choice, light selection included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitterSample<'a> {
+    pub direction: Vector<3>,
+    pub hit: Hit<'a>,
+    pub radiance: RGB,
+    pub pdf: f64,
+}
+
+// The tree is built from the world it indexes, so only its size is shown.
+impl fmt::Debug for LightBvh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LightBvh")
+            .field("emitters", &self.emitters.len())
+            .field("nodes", &self.nodes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LightBvh {
+    // Collects the spheres in `world` whose material emits. `None` if
+    // there are none.
+    pub fn new(world: &Hittable) -> Option<Self> {
+        let targets: &[Target] = match world {
+            Hittable::Single(target) => std::slice::from_ref(target),
+            Hittable::Multiple(list) => &list.list,
+            Hittable::MultiplePtr(list) => &list.list,
+        };
+        let emitters: Vec<SphereData> = targets
+            .iter()
+            .map(|target| match target {
+                Target::Sphere(sphere) => sphere,
+            })
+            .filter(|sphere| sphere.material.average_emission() != RGB::zero())
+            .cloned()
+            .collect();
+        if emitters.is_empty() {
+            return None;
+        }
+
+        let mut bvh = Self {
+            emitters,
+            nodes: Vec::new(),
+            trails: BTreeMap::new(),
+        };
+        let mut indices: Vec<usize> = (0..bvh.emitters.len()).collect();
+        bvh.build(&mut indices, 0, 0);
+        Some(bvh)
+    }
+
+    // Splits the emitters at the median of their centres along the widest
+    // axis, recording how to reach each one from the root.
+    fn build(&mut self, indices: &mut [usize], trail: u64, depth: u32) -> LightBounds {
+        if indices.len() == 1 || depth == 63 {
+            let index = indices[0];
+            let bounds = LightBounds::sphere(&self.emitters[index]);
+            self.trails.insert(key(&self.emitters[index]), trail);
+            self.nodes.push(LightNode {
+                bounds,
+                kind: NodeKind::Leaf(index),
+            });
+            return bounds;
+        }
+
+        let (min, max) = indices.iter().fold(
+            (
+                Vector::new([f64::INFINITY; 3]),
+                Vector::new([f64::NEG_INFINITY; 3]),
+            ),
+            |(min, max), &i| {
+                let c = self.emitters[i].center.components;
+                (
+                    Vector::new(std::array::from_fn(|a| min.components[a].min(c[a]))),
+                    Vector::new(std::array::from_fn(|a| max.components[a].max(c[a]))),
+                )
+            },
+        );
+        let extent = (max - min).components;
+        let axis = (0..3)
+            .max_by(|&a, &b| extent[a].total_cmp(&extent[b]))
+            .unwrap_or(0);
+        indices.sort_by(|&a, &b| {
+            let ca = self.emitters[a].center.components[axis];
+            let cb = self.emitters[b].center.components[axis];
+            ca.total_cmp(&cb)
+        });
+
+        let node = self.nodes.len();
+        self.nodes.push(LightNode {
+            bounds: LightBounds::empty(),
+            kind: NodeKind::Interior(0),
+        });
+        let (left, right) = indices.split_at_mut(indices.len() / 2);
+        let left_bounds = self.build(left, trail, depth + 1);
+        let second = self.nodes.len();
+        let right_bounds = self.build(right, trail | (1 << depth), depth + 1);
+
+        let bounds = left_bounds.union(&right_bounds);
+        self.nodes[node] = LightNode {
+            bounds,
+            kind: NodeKind::Interior(second),
+        };
+        bounds
+    }
+
+    // Picks an emitter for the shading point `p` with normal `n`, then a
+    // direction inside the cone it subtends.
+    pub fn sample(&self, p: Vector<3>, n: Vector<3>) -> Option<EmitterSample<'_>> {
+        let mut node = 0;
+        let mut pmf = 1.0;
+        let mut u = fastrand::f64();
+        loop {
+            match self.nodes[node].kind {
+                NodeKind::Leaf(index) => {
+                    if self.nodes[node].bounds.importance(p, n) <= 0.0 {
+                        return None;
+                    }
+                    return self.sample_emitter(&self.emitters[index], p, pmf);
+                }
+                NodeKind::Interior(second) => {
+                    let left = self.nodes[node + 1].bounds.importance(p, n);
+                    let right = self.nodes[second].bounds.importance(p, n);
+                    if left + right <= 0.0 {
+                        return None;
+                    }
+                    let p_left = left / (left + right);
+                    if u < p_left {
+                        u /= p_left;
+                        pmf *= p_left;
+                        node += 1;
+                    } else {
+                        u = ((u - p_left) / (1.0 - p_left)).min(1.0 - f64::EPSILON);
+                        pmf *= 1.0 - p_left;
+                        node = second;
+                    }
+                }
+            }
+        }
+    }
+
+    // Density with which `sample` from `p` and `n` would have produced a
+    // ray hitting the emitter at `hit`. Zero if `hit` is not an emitter.
+    pub fn pdf(&self, p: Vector<3>, n: Vector<3>, hit: &Hit) -> f64 {
+        let Some(&trail) = self.trails.get(&key(hit.sphere)) else {
+            return 0.0;
+        };
+
+        let mut node = 0;
+        let mut pmf = 1.0;
+        let mut depth = 0;
+        while let NodeKind::Interior(second) = self.nodes[node].kind {
+            let left = self.nodes[node + 1].bounds.importance(p, n);
+            let right = self.nodes[second].bounds.importance(p, n);
+            if left + right <= 0.0 {
+                return 0.0;
+            }
+            if trail & (1 << depth) == 0 {
+                pmf *= left / (left + right);
+                node += 1;
+            } else {
+                pmf *= right / (left + right);
+                node = second;
+            }
+            depth += 1;
+        }
+        if self.nodes[node].bounds.importance(p, n) <= 0.0 {
+            return 0.0;
+        }
+        match cone(hit.sphere, p) {
+            Some(cos_max) => pmf / (2.0 * PI * (1.0 - cos_max)),
+            None => 0.0,
+        }
+    }
+
+    fn sample_emitter<'a>(
+        &self,
+        sphere: &'a SphereData,
+        p: Vector<3>,
+        pmf: f64,
+    ) -> Option<EmitterSample<'a>> {
+        let cos_max = cone(sphere, p)?;
+        let cos_theta = 1.0 - fastrand::f64() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * fastrand::f64();
+        let local = Vector::new([sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta]);
+        let direction = Onb::new(sphere.center - p).to_world(local);
+
+        // The cone's rim grazes the sphere, where rounding may miss it.
+        let hit = sphere.hit(Ray::new(p, direction), Interval::new(0.0, f64::INFINITY))?;
+        Some(EmitterSample {
+            direction,
+            radiance: hit.material.emitted(&hit),
+            hit,
+            pdf: pmf / (2.0 * PI * (1.0 - cos_max)),
+        })
+    }
+}
+
+// Cosine of the half angle `sphere` subtends from `p`, or `None` from
+// inside it.
+fn cone(sphere: &SphereData, p: Vector<3>) -> Option<f64> {
+    let distance_squared = (sphere.center - p).length_squared();
+    let radius_squared = sphere.radius * sphere.radius;
+    if distance_squared <= radius_squared {
+        return None;
+    }
+    Some((1.0 - radius_squared / distance_squared).max(0.0).sqrt())
+}
+
+fn key(sphere: &SphereData) -> [u64; 4] {
+    let [x, y, z] = sphere.center.components;
+    [
+        x.to_bits(),
+        y.to_bits(),
+        z.to_bits(),
+        sphere.radius.to_bits(),
+    ]
+}
+
+// Spatial, power and directional bounds of a group of emitters. Emission
+// leaves within `cos_theta_e` of a direction inside the cone around `w`
+// with half angle acos(`cos_theta_o`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LightBounds {
+    min: Vector<3>,
+    max: Vector<3>,
+    phi: f64, // total emitted power
+    w: Vector<3>,
+    cos_theta_o: f64,
+    cos_theta_e: f64,
+}
+
+impl LightBounds {
+    fn empty() -> Self {
+        Self {
+            min: Vector::new([f64::INFINITY; 3]),
+            max: Vector::new([f64::NEG_INFINITY; 3]),
+            phi: 0.0,
+            w: Vector::new([0.0, 0.0, 1.0]),
+            cos_theta_o: 1.0,
+            cos_theta_e: 1.0,
+        }
+    }
+
+    // A sphere emits from every point along its outward normal, which
+    // covers all directions.
+    fn sphere(sphere: &SphereData) -> Self {
+        let r = Vector::new([sphere.radius; 3]);
+        let emission = sphere.material.average_emission();
+        let area = 4.0 * PI * sphere.radius * sphere.radius;
+        Self {
+            min: sphere.center - r,
+            max: sphere.center + r,
+            phi: emission.components.iter().sum::<f64>() / 3.0 * area * PI,
+            w: Vector::new([0.0, 0.0, 1.0]),
+            cos_theta_o: -1.0,
+            cos_theta_e: 0.0,
+        }
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        let (w, cos_theta_o) =
+            union_cones((self.w, self.cos_theta_o), (other.w, other.cos_theta_o));
+        Self {
+            min: Vector::new(std::array::from_fn(|a| {
+                self.min.components[a].min(other.min.components[a])
+            })),
+            max: Vector::new(std::array::from_fn(|a| {
+                self.max.components[a].max(other.max.components[a])
+            })),
+            phi: self.phi + other.phi,
+            w,
+            cos_theta_o,
+            cos_theta_e: self.cos_theta_e.min(other.cos_theta_e),
+        }
+    }
+
+    // Upper bound on the contribution to point `p` with normal `n`: power
+    // over squared distance, reduced by the smallest angle between the
+    // emission cone and `p`, and by the surface's cosine towards the box.
+    fn importance(&self, p: Vector<3>, n: Vector<3>) -> f64 {
+        let center = 0.5 * (self.min + self.max);
+        let diagonal = (self.max - self.min).length();
+        let d2 = (p - center).length_squared().max(diagonal / 2.0);
+
+        let cos_sub = |sin_a: f64, cos_a: f64, sin_b: f64, cos_b: f64| {
+            if cos_a > cos_b {
+                1.0
+            } else {
+                cos_a * cos_b + sin_a * sin_b
+            }
+        };
+        let sin_sub = |sin_a: f64, cos_a: f64, sin_b: f64, cos_b: f64| {
+            if cos_a > cos_b {
+                0.0
+            } else {
+                sin_a * cos_b - cos_a * sin_b
+            }
+        };
+        let sin_of = |cos: f64| (1.0 - cos * cos).max(0.0).sqrt();
+
+        let wi = (p - center).normalize();
+        let cos_theta_w = self.w.dot(wi);
+        let sin_theta_w = sin_of(cos_theta_w);
+
+        let cos_theta_b = self.subtended(p);
+        let sin_theta_b = sin_of(cos_theta_b);
+
+        let sin_theta_o = sin_of(self.cos_theta_o);
+        let cos_theta_x = cos_sub(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let sin_theta_x = sin_sub(sin_theta_w, cos_theta_w, sin_theta_o, self.cos_theta_o);
+        let cos_theta_p = cos_sub(sin_theta_x, cos_theta_x, sin_theta_b, cos_theta_b);
+        if cos_theta_p <= self.cos_theta_e {
+            return 0.0;
+        }
+
+        let mut importance = self.phi * cos_theta_p / d2;
+        if n != Vector::zero() {
+            let cos_theta_i = wi.dot(n).abs();
+            let sin_theta_i = sin_of(cos_theta_i);
+            importance *= cos_sub(sin_theta_i, cos_theta_i, sin_theta_b, cos_theta_b);
+        }
+        importance.max(0.0)
+    }
+
+    // Cosine of the half angle of a cone from `p` containing the box.
+    fn subtended(&self, p: Vector<3>) -> f64 {
+        let inside = (0..3).all(|a| {
+            self.min.components[a] <= p.components[a] && p.components[a] <= self.max.components[a]
+        });
+        if inside {
+            return -1.0;
+        }
+        let center = 0.5 * (self.min + self.max);
+        let radius_squared = (0.5 * (self.max - self.min)).length_squared();
+        let distance_squared = (p - center).length_squared();
+        if distance_squared < radius_squared {
+            return -1.0;
+        }
+        (1.0 - radius_squared / distance_squared).max(0.0).sqrt()
+    }
+}
+
+// Smallest cone containing two cones, each a direction and the cosine of
+// its half angle.
+fn union_cones(a: (Vector<3>, f64), b: (Vector<3>, f64)) -> (Vector<3>, f64) {
+    let (theta_a, theta_b) = (a.1.clamp(-1.0, 1.0).acos(), b.1.clamp(-1.0, 1.0).acos());
+    let theta_d = a.0.dot(b.0).clamp(-1.0, 1.0).acos();
+    if (theta_d + theta_b).min(PI) <= theta_a {
+        return a;
+    }
+    if (theta_d + theta_a).min(PI) <= theta_b {
+        return b;
+    }
+
+    let theta_o = (theta_a + theta_d + theta_b) / 2.0;
+    if theta_o >= PI {
+        return (a.0, -1.0);
+    }
+    // Rotate a's axis towards b's until it sits in the middle of both.
+    let theta_r = theta_o - theta_a;
+    let axis = a.0.cross(b.0);
+    if axis.length_squared() < 1e-12 {
+        return (a.0, -1.0);
+    }
+    let axis = axis.normalize();
+    let v = a.0;
+    let w = v * theta_r.cos()
+        + axis.cross(v) * theta_r.sin()
+        + axis * (axis.dot(v) * (1.0 - theta_r.cos()));
+    (w, theta_o.cos())
+}
@@ -1,18 +1,147 @@
-use super::{DielectricData, Hit, LambertianData, MetalData, Ray, ScatterResult};
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use super::{
+    BumpData, CoatedData, ConductorData, CutoutData, DielectricData, Hit, LambertianData, Medium,
+    MetalData, MixData, OrenNayarData, PrincipledData, Ray, RoughDielectricData, ScatterResult,
+    TranslucentData, Vector, RGB,
+};
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum Material {
     Lambertian(LambertianData),
     Metal(MetalData),
     Dielectric(DielectricData),
+    Conductor(ConductorData),
+    RoughDielectric(RoughDielectricData),
+    OrenNayar(OrenNayarData),
+    Translucent(TranslucentData),
+    Principled(Arc<PrincipledData>), // shared, since materials are cloned with their scene
+    Mix(Arc<MixData>),
+    Coated(Arc<CoatedData>),
+    Bump(Arc<BumpData>),
+    Cutout(Arc<CutoutData>),
 }
 
 impl Material {
-    pub fn scatter(self, incoming: Ray, hit: Hit) -> ScatterResult {
+    // Samples a scattered direction. `None` means the path is absorbed.
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
         match self {
             Material::Lambertian(data) => data.scatter(incoming, hit),
             Material::Metal(data) => data.scatter(incoming, hit),
             Material::Dielectric(data) => data.scatter(incoming, hit),
+            Material::Conductor(data) => data.scatter(incoming, hit),
+            Material::RoughDielectric(data) => data.scatter(incoming, hit),
+            Material::OrenNayar(data) => data.scatter(incoming, hit),
+            Material::Translucent(data) => data.scatter(incoming, hit),
+            Material::Principled(data) => data.scatter(incoming, hit),
+            Material::Mix(data) => data.scatter(incoming, hit),
+            Material::Coated(data) => data.scatter(incoming, hit),
+            Material::Bump(data) => data.scatter(incoming, hit),
+            Material::Cutout(data) => data.scatter(incoming, hit),
+        }
+    }
+
+    // BSDF times |cos| for light arriving from `direction`; zero for the
+    // delta lobes that only `scatter` can produce.
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        match self {
+            Material::Lambertian(data) => data.eval(incoming, hit, direction),
+            Material::Metal(data) => data.eval(incoming, hit, direction),
+            Material::Dielectric(data) => data.eval(incoming, hit, direction),
+            Material::Conductor(data) => data.eval(incoming, hit, direction),
+            Material::RoughDielectric(data) => data.eval(incoming, hit, direction),
+            Material::OrenNayar(data) => data.eval(incoming, hit, direction),
+            Material::Translucent(data) => data.eval(incoming, hit, direction),
+            Material::Principled(data) => data.eval(incoming, hit, direction),
+            Material::Mix(data) => data.eval(incoming, hit, direction),
+            Material::Coated(data) => data.eval(incoming, hit, direction),
+            Material::Bump(data) => data.eval(incoming, hit, direction),
+            Material::Cutout(data) => data.eval(incoming, hit, direction),
+        }
+    }
+
+    // Solid angle density with which `scatter` picks `direction`, for
+    // multiple importance sampling.
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        match self {
+            Material::Lambertian(data) => data.pdf(incoming, hit, direction),
+            Material::Metal(data) => data.pdf(incoming, hit, direction),
+            Material::Dielectric(data) => data.pdf(incoming, hit, direction),
+            Material::Conductor(data) => data.pdf(incoming, hit, direction),
+            Material::RoughDielectric(data) => data.pdf(incoming, hit, direction),
+            Material::OrenNayar(data) => data.pdf(incoming, hit, direction),
+            Material::Translucent(data) => data.pdf(incoming, hit, direction),
+            Material::Principled(data) => data.pdf(incoming, hit, direction),
+            Material::Mix(data) => data.pdf(incoming, hit, direction),
+            Material::Coated(data) => data.pdf(incoming, hit, direction),
+            Material::Bump(data) => data.pdf(incoming, hit, direction),
+            Material::Cutout(data) => data.pdf(incoming, hit, direction),
+        }
+    }
+
+    // Radiance the surface emits towards the viewer.
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        match self {
+            Material::Principled(data) => data.emitted(hit),
+            Material::Mix(data) => data.emitted(hit),
+            Material::Coated(data) => data.emitted(hit),
+            Material::Bump(data) => data.emitted(hit),
+            Material::Cutout(data) => data.emitted(hit),
+            _ => RGB::zero(),
+        }
+    }
+
+    // Emitted radiance averaged over the surface, zero for materials that
+    // never emit.
+    pub fn average_emission(&self) -> RGB {
+        match self {
+            Material::Principled(data) => data.emission.average(),
+            Material::Mix(data) => {
+                let weight = data.amount.average().components.iter().sum::<f64>() / 3.0;
+                let weight = weight.clamp(0.0, 1.0);
+                (1.0 - weight) * data.a.average_emission() + weight * data.b.average_emission()
+            }
+            Material::Coated(data) => data.base.average_emission(),
+            Material::Bump(data) => data.base.average_emission(),
+            Material::Cutout(data) => data.base.average_emission(),
+            _ => RGB::zero(),
+        }
+    }
+
+    // Medium inside closed objects with this material, if it absorbs.
+    pub fn medium(&self) -> Option<Medium> {
+        match self {
+            Material::Dielectric(data) => data.medium(),
+            Material::RoughDielectric(data) => data.medium(),
+            Material::Mix(data) => data.medium(),
+            Material::Coated(data) => data.medium(),
+            Material::Bump(data) => data.medium(),
+            Material::Cutout(data) => data.medium(),
+            _ => None,
+        }
+    }
+
+    // Whether scattering depends on the ray's wavelength.
+    pub fn is_dispersive(&self) -> bool {
+        match self {
+            Material::Dielectric(data) => data.is_dispersive(),
+            Material::Mix(data) => data.is_dispersive(),
+            Material::Coated(data) => data.is_dispersive(),
+            Material::Bump(data) => data.is_dispersive(),
+            Material::Cutout(data) => data.is_dispersive(),
+            _ => false,
+        }
+    }
+
+    // Whether a hit should count, or the ray pass through a cut out part.
+    // Stochastic masks answer randomly, so call this once per hit.
+    pub fn is_opaque(&self, hit: &Hit) -> bool {
+        match self {
+            Material::Cutout(data) => data.is_opaque(hit),
+            Material::Mix(data) => data.is_opaque(hit),
+            Material::Coated(data) => data.base.is_opaque(hit),
+            Material::Bump(data) => data.base.is_opaque(hit),
+            _ => true,
         }
     }
 }
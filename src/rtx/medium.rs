@@ -0,0 +1,49 @@
+use super::RGB;
+
+// Homogeneous absorbing interior of a closed dielectric. `absorption` is the
+// Beer-Lambert coefficient per unit of scene distance for each channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Medium {
+    pub absorption: RGB,
+}
+
+impl Medium {
+    pub fn new(absorption: RGB) -> Self {
+        Self { absorption }
+    }
+
+    // Fraction of radiance surviving `distance` through the medium.
+    pub fn transmittance(&self, distance: f64) -> RGB {
+        RGB::new(self.absorption.components.map(|a| (-a * distance).exp()))
+    }
+}
+
+// Media the current path is inside, innermost last. Entering a surface
+// pushes its medium and leaving removes it again, so nested objects (ice
+// in a glass of water) attenuate correctly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediumStack {
+    media: Vec<Medium>,
+}
+
+impl MediumStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> Option<&Medium> {
+        self.media.last()
+    }
+
+    pub fn enter(&mut self, medium: Medium) {
+        self.media.push(medium);
+    }
+
+    // Removes the innermost occurrence of `medium`; objects that overlap
+    // instead of nesting may be left in any order.
+    pub fn exit(&mut self, medium: Medium) {
+        if let Some(index) = self.media.iter().rposition(|m| *m == medium) {
+            self.media.remove(index);
+        }
+    }
+}
@@ -1,5 +1,10 @@
-use super::{Hit, Ray, ScatterResult, Vector, RGB};
+use super::conductor::MicrofacetReflection;
+use super::microfacet::TrowbridgeReitz;
+use super::{fresnel, Hit, Ray, ScatterResult, Vector, RGB};
 
+// Artist friendly metal: GGX reflection tinted by Schlick's approximation
+// with `albedo` as the reflectance at normal incidence. `fuzz` is the
+// perceptual roughness.
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct MetalData {
     pub albedo: RGB,
@@ -10,15 +15,26 @@ impl MetalData {
     pub fn new(albedo: RGB, fuzz: f64) -> Self {
         Self {
             albedo,
-            fuzz: fuzz.max(1.0),
+            fuzz: fuzz.clamp(0.0, 1.0),
         }
     }
 
-    pub fn scatter(self, incoming: Ray, hit: Hit) -> ScatterResult {
-        let reflected = incoming.direction.reflect(hit.normal);
-        let fuzzed = reflected.normalize() + (self.fuzz * Vector::<3>::random_unit_vector());
-        let outgoing = Ray::new(hit.p, fuzzed);
+    fn reflection(&self) -> MicrofacetReflection {
+        MicrofacetReflection::new(TrowbridgeReitz::from_roughness(self.fuzz))
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        self.reflection()
+            .scatter(incoming, hit, |cos| fresnel::schlick(cos, self.albedo))
+    }
+
+    pub fn eval(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        self.reflection().eval(incoming, hit, direction, |cos| {
+            fresnel::schlick(cos, self.albedo)
+        })
+    }
 
-        return ScatterResult::new(incoming, hit, self.albedo, outgoing);
+    pub fn pdf(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        self.reflection().pdf(incoming, hit, direction)
     }
 }
@@ -0,0 +1,143 @@
+use std::f64::consts::PI;
+
+use super::Vector;
+
+// Trowbridge-Reitz (GGX) microfacet distribution. All directions are in the
+// local shading frame with the macro surface normal along +z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrowbridgeReitz {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl TrowbridgeReitz {
+    pub fn new(alpha_x: f64, alpha_y: f64) -> Self {
+        Self { alpha_x, alpha_y }
+    }
+
+    // Maps a perceptual roughness in [0, 1] to an isotropic distribution.
+    pub fn from_roughness(roughness: f64) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        Self::new(alpha, alpha)
+    }
+
+    // Below this the surface is treated as a perfect mirror.
+    pub fn effectively_smooth(&self) -> bool {
+        self.alpha_x.max(self.alpha_y) < 1e-3
+    }
+
+    pub fn d(&self, wm: Vector<3>) -> f64 {
+        let tan2_theta = tan2_theta(wm);
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+        let cos4_theta = cos2_theta(wm).powi(2);
+        if cos4_theta < 1e-16 {
+            return 0.0;
+        }
+        let e = tan2_theta
+            * (cos2_phi(wm) / (self.alpha_x * self.alpha_x)
+                + sin2_phi(wm) / (self.alpha_y * self.alpha_y));
+        1.0 / (PI * self.alpha_x * self.alpha_y * cos4_theta * (1.0 + e).powi(2))
+    }
+
+    pub fn lambda(&self, w: Vector<3>) -> f64 {
+        let tan2_theta = tan2_theta(w);
+        if !tan2_theta.is_finite() {
+            return 0.0;
+        }
+        let alpha2 =
+            cos2_phi(w) * self.alpha_x * self.alpha_x + sin2_phi(w) * self.alpha_y * self.alpha_y;
+        ((1.0 + alpha2 * tan2_theta).sqrt() - 1.0) / 2.0
+    }
+
+    pub fn g1(&self, w: Vector<3>) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    // Height correlated masking-shadowing.
+    pub fn g(&self, wo: Vector<3>, wi: Vector<3>) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    // Distribution of normals visible from `w`.
+    pub fn d_visible(&self, w: Vector<3>, wm: Vector<3>) -> f64 {
+        let cos_theta = w.components[2].abs();
+        if cos_theta == 0.0 {
+            return 0.0;
+        }
+        self.g1(w) / cos_theta * self.d(wm) * w.dot(wm).abs()
+    }
+
+    pub fn pdf(&self, w: Vector<3>, wm: Vector<3>) -> f64 {
+        self.d_visible(w, wm)
+    }
+
+    // Samples a visible normal (Heitz 2018): stretch to the hemisphere
+    // configuration, sample a projected disk, then unstretch.
+    pub fn sample_wm(&self, w: Vector<3>) -> Vector<3> {
+        let [x, y, z] = w.components;
+        let mut wh = Vector::new([self.alpha_x * x, self.alpha_y * y, z]).normalize();
+        if wh.components[2] < 0.0 {
+            wh = -1.0 * wh;
+        }
+
+        let t1 = if wh.components[2] < 0.99999 {
+            Vector::new([0.0, 0.0, 1.0]).cross(wh).normalize()
+        } else {
+            Vector::new([1.0, 0.0, 0.0])
+        };
+        let t2 = wh.cross(t1);
+
+        let r = fastrand::f64().sqrt();
+        let phi = 2.0 * PI * fastrand::f64();
+        let px = r * phi.cos();
+        let mut py = r * phi.sin();
+        let h = (1.0 - px * px).sqrt();
+        let s = (1.0 + wh.components[2]) / 2.0;
+        py = (1.0 - s) * h + s * py;
+        let pz = (1.0 - px * px - py * py).max(0.0).sqrt();
+
+        let nh = px * t1 + py * t2 + pz * wh;
+        Vector::new([
+            self.alpha_x * nh.components[0],
+            self.alpha_y * nh.components[1],
+            nh.components[2].max(1e-6),
+        ])
+        .normalize()
+    }
+}
+
+pub fn same_hemisphere(a: Vector<3>, b: Vector<3>) -> bool {
+    a.components[2] * b.components[2] > 0.0
+}
+
+fn cos2_theta(w: Vector<3>) -> f64 {
+    w.components[2] * w.components[2]
+}
+
+fn sin2_theta(w: Vector<3>) -> f64 {
+    (1.0 - cos2_theta(w)).max(0.0)
+}
+
+fn tan2_theta(w: Vector<3>) -> f64 {
+    sin2_theta(w) / cos2_theta(w)
+}
+
+fn cos2_phi(w: Vector<3>) -> f64 {
+    let sin_theta = sin2_theta(w).sqrt();
+    if sin_theta == 0.0 {
+        1.0
+    } else {
+        (w.components[0] / sin_theta).clamp(-1.0, 1.0).powi(2)
+    }
+}
+
+fn sin2_phi(w: Vector<3>) -> f64 {
+    let sin_theta = sin2_theta(w).sqrt();
+    if sin_theta == 0.0 {
+        0.0
+    } else {
+        (w.components[1] / sin_theta).clamp(-1.0, 1.0).powi(2)
+    }
+}
@@ -0,0 +1,85 @@
+use super::{Hit, Material, Medium, Ray, ScatterResult, Texture, Vector, RGB};
+
+// Blend of two materials, e.g. dirt over metal. `amount` is the weight of
+// `b` and may be a mask texture. Each bounce picks one child with that
+// probability, then weights non-specular samples by the blended BSDF and
+// pdf so light sampling sees the same mixture.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MixData {
+    pub a: Material,
+    pub b: Material,
+    pub amount: Texture,
+}
+
+impl MixData {
+    pub fn new(a: Material, b: Material, amount: Texture) -> Self {
+        Self { a, b, amount }
+    }
+
+    fn weight(&self, hit: &Hit) -> f64 {
+        self.amount.scalar(hit.u, hit.v, hit.p).clamp(0.0, 1.0)
+    }
+
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let weight = self.weight(hit);
+        let chosen = if fastrand::f64() < weight {
+            &self.b
+        } else {
+            &self.a
+        };
+        let scatter = chosen.scatter(incoming, hit)?;
+        if scatter.specular {
+            return Some(scatter);
+        }
+
+        let direction = scatter.scattered.direction;
+        let pdf = self.pdf(incoming, hit, direction);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = self.eval(incoming, hit, direction) / pdf;
+        Some(ScatterResult::new(
+            incoming,
+            attenuation,
+            scatter.scattered,
+            pdf,
+        ))
+    }
+
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let weight = self.weight(hit);
+        (1.0 - weight) * self.a.eval(incoming, hit, direction)
+            + weight * self.b.eval(incoming, hit, direction)
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        let weight = self.weight(hit);
+        (1.0 - weight) * self.a.pdf(incoming, hit, direction)
+            + weight * self.b.pdf(incoming, hit, direction)
+    }
+
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        let weight = self.weight(hit);
+        (1.0 - weight) * self.a.emitted(hit) + weight * self.b.emitted(hit)
+    }
+
+    // Either child may be the one a ray passes through, so the interior is
+    // whichever medium they have, `a`'s if both do.
+    pub fn medium(&self) -> Option<Medium> {
+        self.a.medium().or(self.b.medium())
+    }
+
+    pub fn is_dispersive(&self) -> bool {
+        self.a.is_dispersive() || self.b.is_dispersive()
+    }
+
+    // Asks one child, picked by weight, so the blend is as opaque on
+    // average as its parts.
+    pub fn is_opaque(&self, hit: &Hit) -> bool {
+        if fastrand::f64() < self.weight(hit) {
+            self.b.is_opaque(hit)
+        } else {
+            self.a.is_opaque(hit)
+        }
+    }
+}
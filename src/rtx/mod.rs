@@ -1,33 +1,97 @@
+pub mod aperture;
+pub mod bump;
 pub mod camera;
+pub mod checkpoint;
+pub mod coated;
+pub mod conductor;
+pub mod cutout;
 pub mod dielectric;
+pub mod distribution;
+pub mod environment;
+pub mod fresnel;
 pub mod hit;
 pub mod hittable;
+pub mod ies;
 pub mod interval;
 pub mod lambertian;
+pub mod lens;
+pub mod light;
+pub mod light_bvh;
 pub mod material;
+pub mod medium;
 pub mod metal;
+pub mod microfacet;
+pub mod mix;
+pub mod onb;
+pub mod oren_nayar;
+pub mod principled;
+pub mod progress;
+pub mod projection;
 pub mod ray;
 pub mod rgb;
+pub mod rough_dielectric;
 pub mod sample_scene;
 pub mod scatter_result;
+pub mod scene;
+pub mod scene_file;
+pub mod sky;
+pub mod spectrum;
 pub mod sphere;
+pub mod stats;
+pub mod stereo;
 pub mod target;
 pub mod target_list;
+pub mod texture;
+pub mod tile;
+pub mod translucent;
 pub mod vector;
 
-pub use camera::Camera;
+pub use aperture::{Aperture, ApertureMask};
+pub use bump::{BumpData, ShadingMap};
+pub use camera::{Camera, RenderOutput};
+pub use checkpoint::{Checkpoint, CheckpointError, CheckpointOptions};
+pub use coated::CoatedData;
+pub use conductor::ConductorData;
+pub use cutout::CutoutData;
 pub use dielectric::DielectricData;
+pub use environment::EnvironmentMap;
+pub use fresnel::ThinFilm;
 pub use hit::Hit;
 pub use hittable::{Hittable, HittableObject};
+pub use ies::IesProfile;
 pub use interval::Interval;
 pub use lambertian::LambertianData;
+pub use lens::LensSystem;
+pub use light::{
+    Background, DirectionalLightData, Light, LightSample, Lighting, PointLightData, SpotLightData,
+};
+pub use light_bvh::{EmitterSample, LightBvh};
 pub use material::Material;
+pub use medium::{Medium, MediumStack};
 pub use metal::MetalData;
+pub use mix::MixData;
+pub use onb::Onb;
+pub use oren_nayar::OrenNayarData;
+pub use principled::PrincipledData;
+pub use progress::{
+    CancellationToken, RenderControl, RenderObserver, SilentObserver, StderrProgress,
+};
+pub use projection::Projection;
 pub use ray::Ray;
 pub use rgb::RGB;
+pub use rough_dielectric::RoughDielectricData;
 pub use sample_scene::get;
 pub use scatter_result::ScatterResult;
+pub use scene::Scene;
+pub use scene_file::SceneFileError;
+pub use sky::PhysicalSky;
+pub use spectrum::{Dispersion, SampledWavelengths};
 pub use sphere::SphereData;
+pub use stats::RenderStats;
+pub use stereo::{Stereo, StereoLayout};
 pub use target::Target;
 pub use target_list::TargetList;
+pub use texture::Texture;
+pub use tile::{Tile, TileOptions, TileOrder};
+pub use translucent::TranslucentData;
 pub use vector::Vector;
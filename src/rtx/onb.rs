@@ -0,0 +1,33 @@
+use super::Vector;
+
+// Orthonormal basis around a normal, used to move directions into a local
+// shading frame where the normal is +z.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onb {
+    pub u: Vector<3>,
+    pub v: Vector<3>,
+    pub w: Vector<3>,
+}
+
+impl Onb {
+    pub fn new(normal: Vector<3>) -> Self {
+        let w = normal.normalize();
+        let a = if w.components[0].abs() > 0.9 {
+            Vector::new([0.0, 1.0, 0.0])
+        } else {
+            Vector::new([1.0, 0.0, 0.0])
+        };
+        let v = w.cross(a).normalize();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    pub fn to_local(&self, world: Vector<3>) -> Vector<3> {
+        Vector::new([world.dot(self.u), world.dot(self.v), world.dot(self.w)])
+    }
+
+    pub fn to_world(&self, local: Vector<3>) -> Vector<3> {
+        let [x, y, z] = local.components;
+        x * self.u + y * self.v + z * self.w
+    }
+}
@@ -0,0 +1,83 @@
+use std::f64::consts::PI;
+
+use super::{Hit, Onb, Ray, ScatterResult, Vector, RGB};
+
+// Rough diffuse surface made of V-shaped Lambertian facets, using the
+// qualitative Oren-Nayar model. `sigma` is the standard deviation of the
+// facet slopes in radians; zero reduces to Lambertian. Rough surfaces look
+// flatter and back-scatter towards the light, as clay and fabric do.
+// Sampling is cosine weighted, which the lobe stays close to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrenNayarData {
+    pub albedo: RGB,
+    pub sigma: f64,
+}
+
+impl OrenNayarData {
+    pub fn new(albedo: RGB, sigma: f64) -> Self {
+        Self {
+            albedo,
+            sigma: sigma.max(0.0),
+        }
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let scattered = hit.normal + Vector::random_unit_vector();
+        let direction = if scattered.near_zero() {
+            hit.normal
+        } else {
+            scattered.normalize()
+        };
+        let pdf = self.pdf(incoming, hit, direction);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = self.eval(incoming, hit, direction) / pdf;
+        Some(ScatterResult::new(
+            incoming,
+            attenuation,
+            Ray::new(hit.p, direction),
+            pdf,
+        ))
+    }
+
+    pub fn eval(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        let cos_theta_i = wi.components[2];
+        if cos_theta_i <= 0.0 {
+            return RGB::zero();
+        }
+        self.albedo * (self.reflectance(wo, wi) / PI * cos_theta_i)
+    }
+
+    pub fn pdf(self, _incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        hit.normal.dot(direction.normalize()).max(0.0) / PI
+    }
+
+    // The Oren-Nayar factor A + B max(0, cos(phi_i - phi_o)) sin(alpha) tan(beta).
+    fn reflectance(&self, wo: Vector<3>, wi: Vector<3>) -> f64 {
+        let sigma2 = self.sigma * self.sigma;
+        let a = 1.0 - sigma2 / (2.0 * (sigma2 + 0.33));
+        let b = 0.45 * sigma2 / (sigma2 + 0.09);
+
+        let sin_theta = |w: Vector<3>| (1.0 - w.components[2].powi(2)).max(0.0).sqrt();
+        let (sin_i, sin_o) = (sin_theta(wi), sin_theta(wo));
+        let cos_i = wi.components[2].abs();
+        let cos_o = wo.components[2].abs();
+
+        let mut max_cos = 0.0;
+        if sin_i > 1e-4 && sin_o > 1e-4 {
+            let [xi, yi, _] = wi.components;
+            let [xo, yo, _] = wo.components;
+            max_cos = ((xi * xo + yi * yo) / (sin_i * sin_o)).max(0.0);
+        }
+        let (sin_alpha, tan_beta) = if cos_i > cos_o {
+            (sin_o, sin_i / cos_i)
+        } else {
+            (sin_i, sin_o / cos_o.max(1e-8))
+        };
+        a + b * max_cos * sin_alpha * tan_beta
+    }
+}
@@ -0,0 +1,262 @@
+use std::f64::consts::PI;
+
+use super::conductor::MicrofacetReflection;
+use super::microfacet::TrowbridgeReitz;
+use super::{fresnel, Hit, Onb, Ray, RoughDielectricData, ScatterResult, Texture, Vector, RGB};
+
+const MIN_ROUGHNESS: f64 = 0.05; // keeps every lobe rough so one sample can mix them
+const CLEARCOAT_ROUGHNESS: f64 = 0.1;
+
+// Disney-style uber material. A diffuse base with sheen, a GGX specular
+// layer that turns into a conductor as `metallic` goes to one, a rough
+// glass lobe for `transmission` and a fixed-gloss clear coat on top. Every
+// parameter is a texture. The glass lobe reflects as well as refracts, so
+// the specular layer only covers the part of the surface that is not
+// glass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrincipledData {
+    pub base_color: Texture,
+    pub metallic: Texture,
+    pub roughness: Texture,
+    pub specular: Texture,     // dielectric reflectance, 0.5 is the usual 4%
+    pub sheen: Texture,        // grazing retro-reflection for cloth
+    pub clearcoat: Texture,    // strength of the varnish layer
+    pub transmission: Texture, // fraction of the dielectric base that is glass
+    pub emission: Texture,     // emitted radiance
+    pub refraction_index: f64, // index of the transmission lobe
+}
+
+impl PrincipledData {
+    pub fn new(base_color: Texture) -> Self {
+        Self {
+            base_color,
+            metallic: Texture::constant(0.0),
+            roughness: Texture::constant(0.5),
+            specular: Texture::constant(0.5),
+            sheen: Texture::constant(0.0),
+            clearcoat: Texture::constant(0.0),
+            transmission: Texture::constant(0.0),
+            emission: Texture::constant(0.0),
+            refraction_index: 1.5,
+        }
+    }
+
+    pub fn emitted(&self, hit: &Hit) -> RGB {
+        self.emission.value(hit.u, hit.v, hit.p)
+    }
+
+    pub fn scatter(&self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        if wo.components[2] <= 0.0 {
+            return None;
+        }
+        let lobes = self.lobes(hit, wo);
+        let wi = lobes.sample(wo)?;
+        let pdf = lobes.pdf(wo, wi);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = lobes.eval(wo, wi) / pdf;
+        let outgoing = Ray::new(hit.p, onb.to_world(wi));
+        Some(ScatterResult::new(incoming, attenuation, outgoing, pdf))
+    }
+
+    pub fn eval(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        self.lobes(hit, wo).eval(wo, wi)
+    }
+
+    pub fn pdf(&self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        self.lobes(hit, wo).pdf(wo, wi)
+    }
+
+    // Resolves the textures at `hit` into lobe weights, and picks how often
+    // each lobe is sampled from its rough share of the reflected energy.
+    fn lobes(&self, hit: &Hit, wo: Vector<3>) -> Lobes {
+        let (u, v, p) = (hit.u, hit.v, hit.p);
+        let base_color = self.base_color.value(u, v, p);
+        let metallic = self.metallic.scalar(u, v, p).clamp(0.0, 1.0);
+        let roughness = self.roughness.scalar(u, v, p).clamp(MIN_ROUGHNESS, 1.0);
+        let specular = self.specular.scalar(u, v, p).max(0.0);
+        let transmission = self.transmission.scalar(u, v, p).clamp(0.0, 1.0);
+        let white = RGB::new([1.0, 1.0, 1.0]);
+
+        let specular_f0 = (1.0 - metallic) * (0.08 * specular) * white + metallic * base_color;
+        let glass = RoughDielectricData::new(self.refraction_index, roughness);
+        let cos_theta_o = wo.components[2];
+
+        let mut lobes = Lobes {
+            base_color,
+            diffuse: (1.0 - metallic) * (1.0 - transmission),
+            sheen: self.sheen.scalar(u, v, p).max(0.0),
+            specular_f0,
+            specular: MicrofacetReflection::new(TrowbridgeReitz::from_roughness(roughness)),
+            clearcoat: 0.25 * self.clearcoat.scalar(u, v, p).max(0.0),
+            coat: MicrofacetReflection::new(TrowbridgeReitz::from_roughness(CLEARCOAT_ROUGHNESS)),
+            transmission: (1.0 - metallic) * transmission,
+            glass,
+            eta: glass.eta(hit),
+            probabilities: [0.0; 4],
+        };
+
+        let weights = [
+            lobes.diffuse * mean(base_color),
+            lobes.specular_weight() * mean(fresnel::schlick(cos_theta_o, specular_f0)),
+            lobes.clearcoat * mean(fresnel::schlick(cos_theta_o, 0.04 * white)),
+            lobes.transmission,
+        ];
+        let total: f64 = weights.iter().sum();
+        if total > 0.0 {
+            lobes.probabilities = weights.map(|w| w / total);
+        }
+        lobes
+    }
+}
+
+// Principled parameters resolved at one point, in the local shading frame.
+struct Lobes {
+    base_color: RGB,
+    diffuse: f64,
+    sheen: f64,
+    specular_f0: RGB,
+    specular: MicrofacetReflection,
+    clearcoat: f64,
+    coat: MicrofacetReflection,
+    transmission: f64,
+    glass: RoughDielectricData,
+    eta: f64,
+    probabilities: [f64; 4], // diffuse, specular, clearcoat, transmission
+}
+
+impl Lobes {
+    // The glass lobe does its own reflecting, so the specular layer covers
+    // what is left.
+    fn specular_weight(&self) -> f64 {
+        1.0 - self.transmission
+    }
+
+    fn sample(&self, wo: Vector<3>) -> Option<Vector<3>> {
+        let [diffuse, specular, clearcoat, _] = self.probabilities;
+        let u = fastrand::f64();
+        if u < diffuse {
+            let wi = (Vector::random_unit_vector() + Vector::new([0.0, 0.0, 1.0])).normalize();
+            Some(if wi.near_zero() {
+                Vector::new([0.0, 0.0, 1.0])
+            } else {
+                wi
+            })
+        } else if u < diffuse + specular {
+            self.specular.sample_local(wo)
+        } else if u < diffuse + specular + clearcoat {
+            self.coat.sample_local(wo)
+        } else {
+            self.glass.sample_local(wo, self.eta)
+        }
+    }
+
+    // Sum of all lobes' BSDF times |cos theta_i|.
+    fn eval(&self, wo: Vector<3>, wi: Vector<3>) -> RGB {
+        let white = RGB::new([1.0, 1.0, 1.0]);
+        let cos_theta_i = wi.components[2];
+        let mut f = RGB::zero();
+
+        if cos_theta_i > 0.0 {
+            f = f + self.base_color * (self.diffuse / PI * cos_theta_i);
+            if self.sheen > 0.0 {
+                let cos_theta_d = wi.dot((wo + wi).normalize());
+                let weight = (1.0 - cos_theta_d.clamp(0.0, 1.0)).powi(5);
+                f = f + white * (self.diffuse * self.sheen * weight * cos_theta_i);
+            }
+            f = f + self
+                .specular
+                .eval_local(wo, wi, &|cos| fresnel::schlick(cos, self.specular_f0))
+                * self.specular_weight();
+            if self.clearcoat > 0.0 {
+                let coat = self
+                    .coat
+                    .eval_local(wo, wi, &|cos| fresnel::schlick(cos, 0.04 * white));
+                f = f + coat * self.clearcoat;
+            }
+        }
+        if self.transmission > 0.0 {
+            f = f + self.base_color * (self.transmission * self.glass.eval_local(wo, wi, self.eta));
+        }
+        f
+    }
+
+    // Mixture density of all the lobes `sample` may pick.
+    fn pdf(&self, wo: Vector<3>, wi: Vector<3>) -> f64 {
+        let [diffuse, specular, clearcoat, transmission] = self.probabilities;
+        let cosine = wi.components[2].max(0.0) / PI;
+        diffuse * cosine
+            + specular * self.specular.pdf_local(wo, wi)
+            + clearcoat * self.coat.pdf_local(wo, wi)
+            + transmission * self.glass.pdf_local(wo, wi, self.eta)
+    }
+}
+
+fn mean(color: RGB) -> f64 {
+    color.components.iter().sum::<f64>() / 3.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtx::{HittableObject, Interval, Material, SphereData};
+    use std::sync::Arc;
+
+    const SAMPLES: usize = 200_000;
+
+    // Fraction of the light arriving at `angle` degrees from the normal that
+    // leaves the surface again, estimated from `scatter` alone as in a white
+    // furnace.
+    fn albedo(material: PrincipledData, angle: f64) -> f64 {
+        let sphere = SphereData::new(
+            Vector::zero(),
+            1.0,
+            Material::Principled(Arc::new(material)),
+        );
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let ray = Ray::new(
+            Vector::new([sin, 1.0 + cos, 0.0]),
+            Vector::new([-sin, -cos, 0.0]),
+        );
+        let hit = sphere
+            .hit(ray, Interval::new(0.001, f64::INFINITY))
+            .unwrap();
+        let Material::Principled(principled) = &sphere.material else {
+            unreachable!();
+        };
+        let total: f64 = (0..SAMPLES)
+            .filter_map(|_| principled.scatter(ray, &hit))
+            .map(|scatter| mean(scatter.attenuation))
+            .sum();
+        total / SAMPLES as f64
+    }
+
+    #[test]
+    fn transmissive_glass_does_not_create_energy() {
+        for roughness in [0.1, 0.5] {
+            let mut glass = PrincipledData::new(Texture::constant(1.0));
+            glass.transmission = Texture::constant(1.0);
+            glass.roughness = Texture::constant(roughness);
+            for angle in [0.0, 45.0, 80.0] {
+                let albedo = albedo(glass.clone(), angle);
+                assert!(
+                    albedo <= 1.01,
+                    "albedo {} at {} degrees, roughness {}",
+                    albedo,
+                    angle,
+                    roughness
+                );
+                assert!(albedo > 0.5);
+            }
+        }
+    }
+}
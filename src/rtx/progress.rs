@@ -0,0 +1,83 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use super::{RenderStats, Tile};
+
+/// Receives render progress. Called from worker threads, so implementations
+/// must be thread safe; all methods default to doing nothing.
+pub trait RenderObserver: Send + Sync {
+    /// `progress` is the completed fraction of the whole render in [0, 1].
+    fn tile_completed(&self, _tile: &Tile, _progress: f64) {}
+
+    /// A progressive render picked up the checkpoint at `path`.
+    fn resumed(&self, _path: &Path, _passes_completed: usize) {}
+
+    fn finished(&self, _stats: &RenderStats) {}
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SilentObserver;
+
+impl RenderObserver for SilentObserver {}
+
+/// Prints a percentage line to stderr and the statistics at the end.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StderrProgress;
+
+impl RenderObserver for StderrProgress {
+    fn tile_completed(&self, _tile: &Tile, progress: f64) {
+        eprint!("\rProgress: {:5.1}%", progress * 100.0);
+    }
+
+    fn resumed(&self, path: &Path, passes_completed: usize) {
+        eprintln!(
+            "Resuming from {} after {} passes",
+            path.display(),
+            passes_completed
+        );
+    }
+
+    fn finished(&self, stats: &RenderStats) {
+        eprintln!("\nDone.\n{}", stats);
+    }
+}
+
+/// Shared flag checked by workers before starting each tile. Clones refer
+/// to the same flag, so one can be handed to e.g. a signal handler.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone)]
+pub struct RenderControl {
+    pub observer: Arc<dyn RenderObserver>,
+    pub cancel: CancellationToken,
+}
+
+impl RenderControl {
+    pub fn new(observer: Arc<dyn RenderObserver>, cancel: CancellationToken) -> Self {
+        Self { observer, cancel }
+    }
+}
+
+impl Default for RenderControl {
+    fn default() -> Self {
+        Self::new(Arc::new(SilentObserver), CancellationToken::new())
+    }
+}
@@ -0,0 +1,87 @@
+use std::f64::consts::PI;
+
+use super::Vector;
+
+// How the camera maps points on the image to rays. Directions below are in
+// the camera's frame: x to the right, y up, looking down -z.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Projection {
+    #[default]
+    Perspective, // pinhole or thin lens, through the viewport at the focus distance
+    Orthographic,    // parallel rays through that same viewport, for elevations
+    Fisheye,         // equidistant; `vfov` spans the image height and may exceed 180
+    Equirectangular, // the whole sphere, longitude across and latitude down; render at 2:1
+    Cubemap,         // six 90 degree faces side by side; render at 6:1
+}
+
+impl Projection {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "perspective" => Some(Projection::Perspective),
+            "orthographic" => Some(Projection::Orthographic),
+            "fisheye" => Some(Projection::Fisheye),
+            "equirectangular" => Some(Projection::Equirectangular),
+            "cubemap" => Some(Projection::Cubemap),
+            _ => None,
+        }
+    }
+
+    // Direction through (s, t) on the image, each running from 0 at the
+    // top left to 1 at the bottom right, for the panoramic projections.
+    // `None` outside a fisheye's image circle or for a planar projection.
+    pub fn direction(&self, s: f64, t: f64, aspect_ratio: f64, vfov: f64) -> Option<Vector<3>> {
+        match self {
+            Projection::Perspective | Projection::Orthographic => None,
+            Projection::Fisheye => fisheye(s, t, aspect_ratio, vfov),
+            Projection::Equirectangular => Some(equirectangular(s, t)),
+            Projection::Cubemap => Some(cubemap(s, t)),
+        }
+    }
+}
+
+// The angle from the axis grows in proportion to the distance from the
+// image centre, reaching half of `vfov` (radians) at the top and bottom
+// edges.
+fn fisheye(s: f64, t: f64, aspect_ratio: f64, vfov: f64) -> Option<Vector<3>> {
+    let x = (2.0 * s - 1.0) * aspect_ratio;
+    let y = 1.0 - 2.0 * t;
+    let theta = (x * x + y * y).sqrt() * vfov / 2.0;
+    if theta > PI {
+        return None;
+    }
+    let phi = y.atan2(x);
+    Some(Vector::new([
+        theta.sin() * phi.cos(),
+        theta.sin() * phi.sin(),
+        -theta.cos(),
+    ]))
+}
+
+// Straight ahead at the centre, behind at the left and right edges.
+fn equirectangular(s: f64, t: f64) -> Vector<3> {
+    let longitude = (s - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - t) * PI;
+    Vector::new([
+        latitude.cos() * longitude.sin(),
+        latitude.sin(),
+        -latitude.cos() * longitude.cos(),
+    ])
+}
+
+// Faces in the usual +x, -x, +y, -y, +z, -z order, so right, left, up,
+// down, behind and ahead. Each is seen upright as if turning the head
+// towards it; the up and down faces have ahead at their bottom and top.
+fn cubemap(s: f64, t: f64) -> Vector<3> {
+    let face = ((s * 6.0) as usize).min(5);
+    let a = (s * 6.0 - face as f64) * 2.0 - 1.0; // across the face, left to right
+    let b = 1.0 - 2.0 * t; // up the face
+    let (forward, right, up) = match face {
+        0 => ([1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        1 => ([-1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        2 => ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        3 => ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        4 => ([0.0, 0.0, 1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        _ => ([0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    };
+    (Vector::new(forward) + a * Vector::new(right) + b * Vector::new(up)).normalize()
+}
@@ -4,14 +4,23 @@ use super::Vector;
 pub struct Ray {
     pub origin: Vector<3>,
     pub direction: Vector<3>,
+    pub wavelength: Option<f64>, // hero wavelength in nm when rendering spectrally
 }
 
 impl Ray {
     pub fn new(origin: Vector<3>, direction: Vector<3>) -> Self {
-        Self { origin, direction }
+        Self {
+            origin,
+            direction,
+            wavelength: None,
+        }
+    }
+
+    pub fn with_wavelength(self, wavelength: Option<f64>) -> Self {
+        Self { wavelength, ..self }
     }
 
     pub fn at(&self, t: f64) -> Vector<3> {
-        return self.origin + t * self.direction;
+        self.origin + t * self.direction
     }
 }
@@ -0,0 +1,192 @@
+use super::microfacet::{same_hemisphere, TrowbridgeReitz};
+use super::{fresnel, Hit, Medium, Onb, Ray, ScatterResult, Vector, RGB};
+
+// Frosted glass: GGX reflection plus microfacet transmission (Walter et al.
+// 2007). Directions are handled in a frame around the normal facing the
+// incoming ray, so `wo` is always in the upper hemisphere and transmitted
+// directions are below it. Like `DielectricData`, transmission is not
+// scaled by 1 / eta^2, which cancels out for closed objects anyway.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoughDielectricData {
+    pub refraction_index: f64,
+    pub roughness: f64,
+    pub absorption: RGB, // Beer-Lambert coefficient of the interior
+}
+
+impl RoughDielectricData {
+    pub fn new(refraction_index: f64, roughness: f64) -> Self {
+        Self {
+            refraction_index,
+            roughness: roughness.clamp(0.0, 1.0),
+            absorption: RGB::zero(),
+        }
+    }
+
+    pub fn with_absorption(self, absorption: RGB) -> Self {
+        Self { absorption, ..self }
+    }
+
+    pub fn medium(self) -> Option<Medium> {
+        (self.absorption != RGB::zero()).then(|| Medium::new(self.absorption))
+    }
+
+    fn distribution(&self) -> TrowbridgeReitz {
+        TrowbridgeReitz::from_roughness(self.roughness)
+    }
+
+    // Index on the far side of the surface relative to the near side.
+    pub fn eta(&self, hit: &Hit) -> f64 {
+        if hit.front_face {
+            self.refraction_index
+        } else {
+            1.0 / self.refraction_index
+        }
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        if wo.components[2] <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(hit);
+        let distribution = self.distribution();
+        let white = RGB::new([1.0, 1.0, 1.0]);
+
+        if distribution.effectively_smooth() {
+            let [x, y, z] = wo.components;
+            let wi = if fresnel::dielectric(z, eta) > fastrand::f64() {
+                Vector::new([-x, -y, z])
+            } else {
+                refract(wo, Vector::new([0.0, 0.0, 1.0]), eta)?
+            };
+            let outgoing = Ray::new(hit.p, onb.to_world(wi));
+            return Some(ScatterResult::specular(incoming, white, outgoing));
+        }
+
+        let wi = self.sample_local(wo, eta)?;
+        let pdf = self.pdf_local(wo, wi, eta);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = white * (self.eval_local(wo, wi, eta) / pdf);
+        let outgoing = Ray::new(hit.p, onb.to_world(wi));
+        Some(ScatterResult::new(incoming, attenuation, outgoing, pdf))
+    }
+
+    pub fn eval(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        if self.distribution().effectively_smooth() {
+            return RGB::zero();
+        }
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        RGB::new([1.0, 1.0, 1.0]) * self.eval_local(wo, wi, self.eta(hit))
+    }
+
+    pub fn pdf(self, incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        if self.distribution().effectively_smooth() {
+            return 0.0;
+        }
+        let onb = Onb::new(hit.normal);
+        let wo = onb.to_local(-1.0 * incoming.direction.normalize());
+        let wi = onb.to_local(direction.normalize());
+        self.pdf_local(wo, wi, self.eta(hit))
+    }
+
+    // The methods below work in the local frame of the rough lobe, with `wo`
+    // above the surface and `eta` relative to its side.
+
+    pub fn sample_local(&self, wo: Vector<3>, eta: f64) -> Option<Vector<3>> {
+        let wm = self.distribution().sample_wm(wo);
+        let reflectance = fresnel::dielectric(wo.dot(wm), eta);
+
+        if reflectance > fastrand::f64() {
+            let wi = wo.reflect(wm) * -1.0;
+            same_hemisphere(wo, wi).then_some(wi)
+        } else {
+            let wi = refract(wo, wm, eta)?;
+            (!same_hemisphere(wo, wi) && wi.components[2] != 0.0).then_some(wi)
+        }
+    }
+
+    // BSDF times |cos theta_i|.
+    pub fn eval_local(&self, wo: Vector<3>, wi: Vector<3>, eta: f64) -> f64 {
+        let cos_theta_o = wo.components[2];
+        let cos_theta_i = wi.components[2];
+        if cos_theta_o <= 0.0 || cos_theta_i == 0.0 {
+            return 0.0;
+        }
+        let reflect = cos_theta_i > 0.0;
+        let Some(wm) = generalized_half_vector(wo, wi, eta, reflect) else {
+            return 0.0;
+        };
+
+        let distribution = self.distribution();
+        let d = distribution.d(wm);
+        let g = distribution.g(wo, wi);
+        let reflectance = fresnel::dielectric(wo.dot(wm), eta);
+
+        if reflect {
+            reflectance * d * g / (4.0 * cos_theta_o)
+        } else {
+            let denom = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+            (1.0 - reflectance) * d * g * (wi.dot(wm) * wo.dot(wm) / (cos_theta_o * denom)).abs()
+        }
+    }
+
+    pub fn pdf_local(&self, wo: Vector<3>, wi: Vector<3>, eta: f64) -> f64 {
+        let cos_theta_i = wi.components[2];
+        if wo.components[2] <= 0.0 || cos_theta_i == 0.0 {
+            return 0.0;
+        }
+        let reflect = cos_theta_i > 0.0;
+        let Some(wm) = generalized_half_vector(wo, wi, eta, reflect) else {
+            return 0.0;
+        };
+
+        let reflectance = fresnel::dielectric(wo.dot(wm), eta);
+        let visible = self.distribution().pdf(wo, wm);
+        if reflect {
+            visible / (4.0 * wo.dot(wm).abs()) * reflectance
+        } else {
+            let denom = (wi.dot(wm) + wo.dot(wm) / eta).powi(2);
+            visible * wi.dot(wm).abs() / denom * (1.0 - reflectance)
+        }
+    }
+}
+
+// Microfacet normal that maps `wo` to `wi`, oriented along +z. Facets seen
+// from behind by either direction cannot contribute.
+fn generalized_half_vector(
+    wo: Vector<3>,
+    wi: Vector<3>,
+    eta: f64,
+    reflect: bool,
+) -> Option<Vector<3>> {
+    let wm = if reflect { wo + wi } else { wo + eta * wi };
+    if wm.length_squared() == 0.0 {
+        return None;
+    }
+    let mut wm = wm.normalize();
+    if wm.components[2] < 0.0 {
+        wm = -1.0 * wm;
+    }
+    if wm.dot(wi) * wi.components[2] < 0.0 || wm.dot(wo) * wo.components[2] < 0.0 {
+        return None;
+    }
+    Some(wm)
+}
+
+// Refracts `wo` (pointing away from the surface, on the same side as `n`)
+// through a boundary with relative index `eta`.
+fn refract(wo: Vector<3>, n: Vector<3>, eta: f64) -> Option<Vector<3>> {
+    let cos_theta_i = n.dot(wo);
+    let sin2_theta_i = (1.0 - cos_theta_i * cos_theta_i).max(0.0);
+    let sin2_theta_t = sin2_theta_i / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return None;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+    Some((-1.0 / eta) * wo + (cos_theta_i / eta - cos_theta_t) * n)
+}
@@ -1,19 +1,18 @@
 use std::sync::Arc;
 
 use super::{
-    Camera, DielectricData, Hittable, LambertianData, Material, MetalData, SphereData, Target,
-    TargetList, Vector, RGB,
+    Camera, DielectricData, Hittable, LambertianData, Material, MetalData, Scene, SphereData,
+    Target, TargetList, Vector, RGB,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct Scene {
-    pub image_width: usize,
-    pub image_height: usize,
-    pub scene: Arc<Hittable>,
-    pub camera: Camera,
-}
+// Fixed so the randomly placed spheres are identical between runs, which
+// checkpoints rely on to recognise the scene.
+const SCENE_SEED: u64 = 0x5eed;
 
 pub fn get() -> Scene {
+    // A local generator, so building the scene leaves the render's seed alone.
+    let mut rng = fastrand::Rng::with_seed(SCENE_SEED);
+
     let image_width = 480;
     let image_height = 320;
     let samples_per_pixel = 100;
@@ -49,26 +48,22 @@ pub fn get() -> Scene {
 
     for a in -2..2 {
         for b in -2..2 {
-            let choose_mat = fastrand::f64();
-            let center = Vector::new([
-                a as f64 + 0.9 * fastrand::f64(),
-                0.2,
-                b as f64 + 0.9 * fastrand::f64(),
-            ]);
+            let choose_mat = rng.f64();
+            let center = Vector::new([a as f64 + 0.9 * rng.f64(), 0.2, b as f64 + 0.9 * rng.f64()]);
 
             if (center - Vector::new([4.0, 0.2, 0.0])).length() > 0.9 {
                 let sphere_material = if choose_mat < 0.8 {
                     // Diffuse
-                    let albedo = RGB::random() * RGB::random();
+                    let albedo = random_rgb(&mut rng) * random_rgb(&mut rng);
                     Material::Lambertian(LambertianData::new(albedo))
                 } else if choose_mat < 0.95 {
                     // Metal
                     let albedo = RGB::new([
-                        (fastrand::f64() + 1.0) / 2.0,
-                        (fastrand::f64() + 1.0) / 2.0,
-                        (fastrand::f64() + 1.0) / 2.0,
+                        (rng.f64() + 1.0) / 2.0,
+                        (rng.f64() + 1.0) / 2.0,
+                        (rng.f64() + 1.0) / 2.0,
                     ]);
-                    let fuzz = fastrand::f64() / 2.0;
+                    let fuzz = rng.f64() / 2.0;
                     Material::Metal(MetalData::new(albedo, fuzz))
                 } else {
                     // Glass
@@ -105,10 +100,9 @@ pub fn get() -> Scene {
         material3,
     )));
 
-    Scene {
-        image_width,
-        image_height,
-        scene: Arc::new(Hittable::Multiple(world)),
-        camera,
-    }
+    Scene::new(camera, Arc::new(Hittable::Multiple(world)))
+}
+
+fn random_rgb(rng: &mut fastrand::Rng) -> RGB {
+    RGB::new([rng.f64(), rng.f64(), rng.f64()])
 }
@@ -1,20 +1,32 @@
-use super::{Hit, Ray, RGB};
+use super::{Ray, RGB};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ScatterResult {
     pub incoming: Ray,
-    pub hit: Hit,
-    pub attenuation: RGB,
+    pub attenuation: RGB, // BSDF * cosine / pdf for the scattered direction
     pub scattered: Ray,
+    pub pdf: f64,       // solid angle density of the scattered direction
+    pub specular: bool, // sampled from a delta lobe, so `pdf` is meaningless
 }
 
 impl ScatterResult {
-    pub fn new(incoming: Ray, hit: Hit, attenuation: RGB, scattered: Ray) -> Self {
+    pub fn new(incoming: Ray, attenuation: RGB, scattered: Ray, pdf: f64) -> Self {
         Self {
             incoming,
-            hit,
             attenuation,
             scattered,
+            pdf,
+            specular: false,
+        }
+    }
+
+    pub fn specular(incoming: Ray, attenuation: RGB, scattered: Ray) -> Self {
+        Self {
+            incoming,
+            attenuation,
+            scattered,
+            pdf: 0.0,
+            specular: true,
         }
     }
 }
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use super::{
+    Camera, CheckpointError, CheckpointOptions, Hittable, Lighting, RenderControl, RenderOutput,
+    TileOptions,
+};
+
+/// A camera together with the objects it looks at and the lights
+/// illuminating them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub camera: Camera,
+    pub world: Arc<Hittable>,
+    pub lighting: Lighting,
+}
+
+impl Scene {
+    pub fn new(camera: Camera, world: Arc<Hittable>) -> Self {
+        Self {
+            camera,
+            world,
+            lighting: Lighting::new(),
+        }
+    }
+
+    pub fn with_lighting(self, lighting: Lighting) -> Self {
+        Self { lighting, ..self }
+    }
+
+    /// Renders the scene on the calling thread, one scanline at a time.
+    pub fn render_st(&self, control: &RenderControl) -> RenderOutput {
+        self.camera
+            .render_st(self.world.clone(), &self.lighting, control)
+    }
+
+    /// Renders the scene with a pool of worker threads pulling tiles.
+    pub fn render_mt(&self, options: &TileOptions, control: &RenderControl) -> RenderOutput {
+        self.camera
+            .render_mt(self.world.clone(), &self.lighting, options, control)
+    }
+
+    /// Renders the scene in passes, checkpointing to disk between them and
+    /// resuming from an earlier checkpoint if one exists.
+    pub fn render_progressive(
+        &self,
+        tile_options: &TileOptions,
+        options: &CheckpointOptions,
+        control: &RenderControl,
+    ) -> Result<RenderOutput, CheckpointError> {
+        self.camera.render_progressive(
+            self.world.clone(),
+            &self.lighting,
+            tile_options,
+            options,
+            control,
+        )
+    }
+}
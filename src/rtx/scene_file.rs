@@ -0,0 +1,765 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use super::{
+    stereo, Aperture, ApertureMask, Background, BumpData, Camera, CoatedData, ConductorData,
+    CutoutData, DielectricData, DirectionalLightData, Dispersion, EnvironmentMap, Hittable,
+    IesProfile, LambertianData, LensSystem, Light, Lighting, Material, MetalData, MixData,
+    OrenNayarData, PhysicalSky, PointLightData, PrincipledData, Projection, RoughDielectricData,
+    Scene, ShadingMap, SphereData, SpotLightData, Stereo, StereoLayout, Target, TargetList,
+    Texture, ThinFilm, TranslucentData, Vector,
+};
+use crate::image::Image;
+
+// Scene files are line based. Blank lines and everything after `#` are
+// ignored; every other line is a directive followed by its arguments:
+//
+//   camera width 480 height 320 spp 100 depth 25 vfov 20
+//   camera lookfrom 13 2 3 lookat 0 0 0 vup 0 1 0 defocus 0.6 focus 10
+//   camera spectral                              # trace wavelengths for dispersion
+//   camera projection fisheye                    # or orthographic, equirectangular, cubemap
+//   camera defocus 2 blades 6 blade_rotation 15  # hexagonal bokeh
+//   camera aperture_mask star.ppm                # or any shape, light where open
+//   camera lens double_gauss.lens aperture 10 film 35   # lens table, stop and film diagonal in mm
+//   camera stereo side_by_side ipd 0.064 convergence 3  # or top_bottom; both eyes in one image
+//   material ground lambertian 0.5 0.5 0.5
+//   material clay oren_nayar 0.7 0.4 0.3 20      # albedo, facet slope deviation in degrees
+//   material paper translucent 0.7 0.7 0.7 0.2 0.2 0.2 0.3  # reflectance, transmittance, optional wrap
+//   material steel metal 0.7 0.6 0.5 0.1
+//   material glass dielectric 1.5
+//   material frosted dielectric 1.5 0.3          # optional roughness
+//   material wine dielectric 1.34 absorption 0.2 2.5 2.0
+//   material prism dielectric bk7                # or: cauchy <a> <b>, sellmeier <b1..3> <c1..3>
+//   material brushed conductor gold 0.3          # or: conductor custom <eta> <k> <roughness>
+//   material bubble dielectric 1.0 film 380 1.33 # thin film thickness in nm and its index
+//   material tinted conductor iron 0.1 film 250 2.4   # films also go on conductors
+//   texture tiles checker 0.5 0.9 0.9 0.9 0.1 0.1 0.1  # cell size, even, odd colours
+//   texture wood image wood.ppm                  # PPM or PFM, relative to the scene file
+//   texture bumps image bumps.ppm raw            # no gamma decoding, for normal and height maps
+//   texture half constant 0.5 0.5 0.5
+//   material car principled base_color 0.8 0.1 0.1 metallic 0.2 roughness half clearcoat 1
+//   material dirty mix steel ground tiles           # a, b and the weight of b
+//   material varnish coated wood 1.5 0.05            # base, coat index, optional roughness
+//   material hammered bump steel bumps 0.01          # base, height texture, scale
+//   material tiled normal_map ground normals 1       # base, normal texture, optional strength
+//   material leaf cutout green leaf_alpha 0.5        # base, alpha texture, optional threshold
+//   material veil cutout white lace stochastic       # keep hits with probability alpha
+//   sphere 0 -1000 0 1000 ground
+//   light point 0 4 0 20 20 20                   # position, intensity
+//   light spot 0 4 0 0 0 0 40 40 40 30 20        # position, target, intensity, cone, falloff start (deg)
+//   light point 0 4 0 20 20 20 ies downlight.ies # IES LM-63 profile, also on spots
+//   light point 0 4 0 1 1 1 ies downlight.ies photometric  # keep the file's candela
+//   light directional -1 -2 -1 2 2 2             # travel direction, irradiance
+//   environment studio.hdr rotation 90 intensity 1.5  # equirectangular .hdr or PFM
+//   sky elevation 30 azimuth 45 turbidity 3 sun_size 0.53  # daylight, angles in degrees
+//
+// Camera keys are optional, may be given in any order and may be spread
+// over several camera lines. A lens takes the place of the projection,
+// stereo and defocus settings and is focused at the focus distance;
+// `aperture` and `film` follow it. Materials must be declared before the
+// spheres that use them, and textures before the materials. Dielectric
+// absorption is per unit of scene distance travelled inside the object.
+//
+// Principled keys are base_color, metallic, roughness, specular, sheen,
+// clearcoat, transmission, emission and ior. Each takes numbers (three for
+// base_color and emission) or the name of a texture; ior is a number.
+// Spheres with emissive materials are also sampled as lights, picked per
+// shading point by how much each could contribute.
+//
+// An IES profile only shapes its light by default: its candela values are
+// scaled so the brightest direction is 1 and the light's intensity is the
+// fixture's peak, replacing the file's photometry. With `photometric` the
+// file's candela, multiplier included, are kept and the light's intensity
+// multiplies them, one candela being one unit of intensity.
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(std::io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneFileError::Io(err) => write!(f, "failed to read scene file: {}", err),
+            SceneFileError::Parse { line, message } => {
+                write!(f, "scene file line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {}
+
+impl From<std::io::Error> for SceneFileError {
+    fn from(err: std::io::Error) -> Self {
+        SceneFileError::Io(err)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Scene, SceneFileError> {
+    let directory = path.parent().unwrap_or(Path::new("."));
+    parse_in(&fs::read_to_string(path)?, directory)
+}
+
+// Image paths are resolved against the working directory.
+pub fn parse(source: &str) -> Result<Scene, SceneFileError> {
+    parse_in(source, Path::new("."))
+}
+
+fn parse_in(source: &str, directory: &Path) -> Result<Scene, SceneFileError> {
+    let mut parser = Parser::new(directory);
+    for (index, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut tokens = Tokens::new(line, index + 1);
+        if let Some(directive) = tokens.next() {
+            parser.directive(directive, &mut tokens)?;
+            tokens.finish()?;
+        }
+    }
+    Ok(parser.finish())
+}
+
+struct Tokens<'a> {
+    tokens: std::iter::Peekable<std::str::SplitWhitespace<'a>>,
+    line: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(line: &'a str, number: usize) -> Self {
+        Self {
+            tokens: line.split_whitespace().peekable(),
+            line: number,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> SceneFileError {
+        SceneFileError::Parse {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.tokens.next()
+    }
+
+    fn has_more(&mut self) -> bool {
+        self.tokens.peek().is_some()
+    }
+
+    fn peek(&mut self) -> Option<&'a str> {
+        self.tokens.peek().copied()
+    }
+
+    fn word(&mut self, what: &str) -> Result<&'a str, SceneFileError> {
+        self.next()
+            .ok_or_else(|| self.error(format!("expected {}", what)))
+    }
+
+    fn number<T: FromStr>(&mut self, what: &str) -> Result<T, SceneFileError> {
+        let token = self.word(what)?;
+        token
+            .parse()
+            .map_err(|_| self.error(format!("invalid {} '{}'", what, token)))
+    }
+
+    fn vector(&mut self, what: &str) -> Result<Vector<3>, SceneFileError> {
+        Ok(Vector::new([
+            self.number(what)?,
+            self.number(what)?,
+            self.number(what)?,
+        ]))
+    }
+
+    fn finish(&mut self) -> Result<(), SceneFileError> {
+        match self.next() {
+            Some(token) => Err(self.error(format!("unexpected '{}'", token))),
+            None => Ok(()),
+        }
+    }
+}
+
+// dielectric <index> [roughness] [absorption r g b], where the index is a
+// number, a dispersion preset, `cauchy a b` or `sellmeier b1 b2 b3 c1 c2 c3`.
+fn dielectric(tokens: &mut Tokens) -> Result<Material, SceneFileError> {
+    let dispersion = match tokens.word("refraction index")? {
+        "cauchy" => Dispersion::Cauchy {
+            a: tokens.number("cauchy coefficient")?,
+            b: tokens.number("cauchy coefficient")?,
+        },
+        "sellmeier" => Dispersion::Sellmeier {
+            b: tokens.vector("sellmeier coefficient")?.components,
+            c: tokens.vector("sellmeier coefficient")?.components,
+        },
+        token => match token.parse::<f64>() {
+            Ok(refraction_index) => return plain_dielectric(tokens, refraction_index),
+            Err(_) => Dispersion::preset(token)
+                .ok_or_else(|| tokens.error(format!("invalid refraction index '{}'", token)))?,
+        },
+    };
+
+    // Dispersion is only modelled for smooth glass, so no roughness here.
+    let mut data = DielectricData::dispersive(dispersion).with_absorption(absorption(tokens)?);
+    if let Some(film) = film(tokens)? {
+        data = data.with_film(film);
+    }
+    Ok(Material::Dielectric(data))
+}
+
+fn plain_dielectric(
+    tokens: &mut Tokens,
+    refraction_index: f64,
+) -> Result<Material, SceneFileError> {
+    let roughness = match tokens.peek() {
+        Some(token) if token != "absorption" && token != "film" => {
+            Some(tokens.number("roughness")?)
+        }
+        _ => None,
+    };
+    let absorption = absorption(tokens)?;
+    let film = film(tokens)?;
+    Ok(match (roughness, film) {
+        (Some(_), Some(_)) => return Err(tokens.error("thin films need a smooth dielectric")),
+        (Some(roughness), None) => Material::RoughDielectric(
+            RoughDielectricData::new(refraction_index, roughness).with_absorption(absorption),
+        ),
+        (None, film) => {
+            let data = DielectricData::new(refraction_index).with_absorption(absorption);
+            Material::Dielectric(match film {
+                Some(film) => data.with_film(film),
+                None => data,
+            })
+        }
+    })
+}
+
+// Optional trailing `film <thickness nm> <refraction index>`.
+fn film(tokens: &mut Tokens) -> Result<Option<ThinFilm>, SceneFileError> {
+    match tokens.peek() {
+        Some("film") => {
+            tokens.next();
+            Ok(Some(ThinFilm::new(
+                tokens.number("film thickness")?,
+                tokens.number("film refraction index")?,
+            )))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn absorption(tokens: &mut Tokens) -> Result<Vector<3>, SceneFileError> {
+    match tokens.peek() {
+        Some("absorption") => {
+            tokens.next();
+            tokens.vector("absorption")
+        }
+        _ => Ok(Vector::zero()),
+    }
+}
+
+struct CameraSettings {
+    image_width: usize,
+    image_height: usize,
+    samples_per_pixel: usize,
+    max_depth: usize,
+    vfov: f64,
+    lookfrom: Vector<3>,
+    lookat: Vector<3>,
+    vup: Vector<3>,
+    defocus_angle: f64,
+    focus_dist: Option<f64>,
+    spectral: bool,
+    projection: Projection,
+    stereo: Option<StereoLayout>,
+    interocular: f64,
+    convergence: Option<f64>, // defaults to the focus distance
+    lens: Option<LensSystem>,
+    blades: usize,       // round aperture below three
+    blade_rotation: f64, // degrees
+    aperture_mask: Option<Arc<ApertureMask>>,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            image_width: 400,
+            image_height: 225,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            vfov: 90.0,
+            lookfrom: Vector::new([0.0, 0.0, 0.0]),
+            lookat: Vector::new([0.0, 0.0, -1.0]),
+            vup: Vector::new([0.0, 1.0, 0.0]),
+            defocus_angle: 0.0,
+            focus_dist: None,
+            spectral: false,
+            projection: Projection::Perspective,
+            stereo: None,
+            interocular: stereo::INTEROCULAR,
+            convergence: None,
+            lens: None,
+            blades: 0,
+            blade_rotation: 0.0,
+            aperture_mask: None,
+        }
+    }
+}
+
+impl CameraSettings {
+    fn build(&self) -> Camera {
+        let mut camera = Camera::new(
+            self.image_width,
+            self.image_height,
+            self.samples_per_pixel,
+            self.max_depth,
+            self.vfov,
+            self.lookfrom,
+            self.lookat,
+            self.vup,
+            self.defocus_angle,
+            self.focus_dist
+                .unwrap_or_else(|| (self.lookfrom - self.lookat).length()),
+        );
+        camera.spectral = self.spectral;
+        camera.projection = self.projection;
+        camera.aperture = match &self.aperture_mask {
+            Some(mask) => Aperture::Mask(mask.clone()),
+            None => Aperture::polygon(self.blades, self.blade_rotation.to_radians()),
+        };
+        camera.lens = self
+            .lens
+            .as_ref()
+            .map(|lens| Arc::new(lens.focused(camera.focus_dist)));
+        camera.stereo = self.stereo.map(|layout| {
+            Stereo::new(
+                layout,
+                self.interocular,
+                self.convergence.unwrap_or(camera.focus_dist),
+            )
+        });
+        camera
+    }
+}
+
+struct Parser {
+    directory: PathBuf, // where relative image paths start
+    camera: CameraSettings,
+    textures: HashMap<String, Texture>,
+    materials: HashMap<String, Material>,
+    world: TargetList,
+    lighting: Lighting,
+}
+
+impl Parser {
+    fn new(directory: &Path) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            camera: CameraSettings::default(),
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            world: TargetList::new(),
+            lighting: Lighting::new(),
+        }
+    }
+
+    fn finish(mut self) -> Scene {
+        let world = Hittable::Multiple(self.world);
+        self.lighting.add_emitters(&world);
+        Scene::new(self.camera.build(), Arc::new(world)).with_lighting(self.lighting)
+    }
+
+    fn directive(&mut self, directive: &str, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        match directive {
+            "camera" => self.camera(tokens),
+            "texture" => self.texture(tokens),
+            "material" => self.material(tokens),
+            "sphere" => self.sphere(tokens),
+            "light" => self.light(tokens),
+            "environment" => self.environment(tokens),
+            "sky" => self.sky(tokens),
+            _ => Err(tokens.error(format!("unknown directive '{}'", directive))),
+        }
+    }
+
+    fn camera(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let camera = &mut self.camera;
+        while tokens.has_more() {
+            match tokens.word("camera key")? {
+                "lens" => {
+                    let file = tokens.word("lens table path")?;
+                    let lens = LensSystem::load(&self.directory.join(file)).map_err(|err| {
+                        tokens.error(format!("failed to load lens '{}': {}", file, err))
+                    })?;
+                    camera.lens = Some(lens);
+                }
+                "blades" => camera.blades = tokens.number("blades")?,
+                "blade_rotation" => camera.blade_rotation = tokens.number("blade rotation")?,
+                "aperture_mask" => {
+                    let file = tokens.word("aperture mask path")?;
+                    let image = Image::load_raw(&self.directory.join(file)).map_err(|err| {
+                        tokens.error(format!("failed to load image '{}': {}", file, err))
+                    })?;
+                    let mask = ApertureMask::new(Arc::new(image)).ok_or_else(|| {
+                        tokens.error(format!("aperture mask '{}' has no open pixels", file))
+                    })?;
+                    camera.aperture_mask = Some(Arc::new(mask));
+                }
+                key @ ("aperture" | "film") => {
+                    let value = tokens.number(key)?;
+                    let lens = camera
+                        .lens
+                        .take()
+                        .ok_or_else(|| tokens.error(format!("'{}' needs a lens first", key)))?;
+                    camera.lens = Some(match key {
+                        "aperture" => lens.with_aperture(value),
+                        _ => lens.with_film_diagonal(value),
+                    });
+                }
+                "width" => camera.image_width = tokens.number("width")?,
+                "height" => camera.image_height = tokens.number("height")?,
+                "spp" => camera.samples_per_pixel = tokens.number("spp")?,
+                "depth" => camera.max_depth = tokens.number("depth")?,
+                "vfov" => camera.vfov = tokens.number("vfov")?,
+                "lookfrom" => camera.lookfrom = tokens.vector("lookfrom")?,
+                "lookat" => camera.lookat = tokens.vector("lookat")?,
+                "vup" => camera.vup = tokens.vector("vup")?,
+                "defocus" => camera.defocus_angle = tokens.number("defocus")?,
+                "focus" => camera.focus_dist = Some(tokens.number("focus")?),
+                "spectral" => camera.spectral = true,
+                "stereo" => {
+                    let name = tokens.word("stereo layout")?;
+                    camera.stereo = Some(StereoLayout::parse(name).ok_or_else(|| {
+                        tokens.error(format!("unknown stereo layout '{}'", name))
+                    })?);
+                }
+                "ipd" => camera.interocular = tokens.number("ipd")?,
+                "convergence" => camera.convergence = Some(tokens.number("convergence")?),
+                "projection" => {
+                    let name = tokens.word("projection")?;
+                    camera.projection = Projection::parse(name)
+                        .ok_or_else(|| tokens.error(format!("unknown projection '{}'", name)))?;
+                }
+                key => return Err(tokens.error(format!("unknown camera key '{}'", key))),
+            }
+        }
+        Ok(())
+    }
+
+    fn texture(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let name = tokens.word("texture name")?;
+        let texture = match tokens.word("texture type")? {
+            "constant" => Texture::Constant(tokens.vector("color")?),
+            "checker" => Texture::Checker {
+                scale: tokens.number("checker scale")?,
+                even: tokens.vector("even color")?,
+                odd: tokens.vector("odd color")?,
+            },
+            "image" => {
+                let file = tokens.word("image path")?;
+                let path = self.directory.join(file);
+                let image = match tokens.peek() {
+                    Some("raw") => {
+                        tokens.next();
+                        Image::load_raw(&path)
+                    }
+                    _ => Image::load(&path),
+                }
+                .map_err(|err| tokens.error(format!("failed to load image '{}': {}", file, err)))?;
+                Texture::Image(Arc::new(image))
+            }
+            kind => return Err(tokens.error(format!("unknown texture type '{}'", kind))),
+        };
+        self.textures.insert(name.to_string(), texture);
+        Ok(())
+    }
+
+    fn principled(&self, tokens: &mut Tokens) -> Result<PrincipledData, SceneFileError> {
+        let mut data = PrincipledData::new(Texture::constant(0.8));
+        while tokens.has_more() {
+            let key = tokens.word("principled key")?;
+            let color = matches!(key, "base_color" | "emission");
+            let texture = match key {
+                "ior" => {
+                    data.refraction_index = tokens.number("ior")?;
+                    continue;
+                }
+                _ => self.texture_value(tokens, key, color)?,
+            };
+            match key {
+                "base_color" => data.base_color = texture,
+                "metallic" => data.metallic = texture,
+                "roughness" => data.roughness = texture,
+                "specular" => data.specular = texture,
+                "sheen" => data.sheen = texture,
+                "clearcoat" => data.clearcoat = texture,
+                "transmission" => data.transmission = texture,
+                "emission" => data.emission = texture,
+                _ => return Err(tokens.error(format!("unknown principled key '{}'", key))),
+            }
+        }
+        Ok(data)
+    }
+
+    // A literal colour or scalar, or the name of a declared texture.
+    fn texture_value(
+        &self,
+        tokens: &mut Tokens,
+        what: &str,
+        color: bool,
+    ) -> Result<Texture, SceneFileError> {
+        match tokens.peek() {
+            Some(token) if token.parse::<f64>().is_err() => {
+                tokens.next();
+                self.textures
+                    .get(token)
+                    .cloned()
+                    .ok_or_else(|| tokens.error(format!("undefined texture '{}'", token)))
+            }
+            _ if color => Ok(Texture::Constant(tokens.vector(what)?)),
+            _ => Ok(Texture::constant(tokens.number(what)?)),
+        }
+    }
+
+    fn material(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let name = tokens.word("material name")?;
+        let material = match tokens.word("material type")? {
+            "lambertian" => Material::Lambertian(LambertianData::new(tokens.vector("albedo")?)),
+            "oren_nayar" => Material::OrenNayar(OrenNayarData::new(
+                tokens.vector("albedo")?,
+                tokens.number::<f64>("sigma")?.to_radians(),
+            )),
+            "translucent" => Material::Translucent(TranslucentData::new(
+                tokens.vector("reflectance")?,
+                tokens.vector("transmittance")?,
+                if tokens.has_more() {
+                    tokens.number("wrap")?
+                } else {
+                    0.0
+                },
+            )),
+            "metal" => Material::Metal(MetalData::new(
+                tokens.vector("albedo")?,
+                tokens.number("fuzz")?,
+            )),
+            "dielectric" => dielectric(tokens)?,
+            "principled" => Material::Principled(Arc::new(self.principled(tokens)?)),
+            "mix" => Material::Mix(Arc::new(MixData::new(
+                self.material_ref(tokens)?,
+                self.material_ref(tokens)?,
+                self.texture_value(tokens, "mix amount", false)?,
+            ))),
+            "bump" => Material::Bump(Arc::new(BumpData::new(
+                self.material_ref(tokens)?,
+                ShadingMap::Height {
+                    texture: self.texture_value(tokens, "height", false)?,
+                    scale: tokens.number("bump scale")?,
+                },
+            ))),
+            "normal_map" => Material::Bump(Arc::new(BumpData::new(
+                self.material_ref(tokens)?,
+                ShadingMap::Normal {
+                    texture: self.texture_value(tokens, "normal", true)?,
+                    strength: if tokens.has_more() {
+                        tokens.number("normal strength")?
+                    } else {
+                        1.0
+                    },
+                },
+            ))),
+            "cutout" => {
+                let base = self.material_ref(tokens)?;
+                let alpha = self.texture_value(tokens, "alpha", false)?;
+                let threshold = match tokens.peek().map(str::parse::<f64>) {
+                    Some(Ok(_)) => Some(tokens.number("alpha threshold")?),
+                    _ => None,
+                };
+                let stochastic = match tokens.peek() {
+                    Some("stochastic") => {
+                        tokens.next();
+                        true
+                    }
+                    _ => false,
+                };
+                let default = if stochastic { 0.0 } else { 0.5 };
+                let data = CutoutData::new(base, alpha, threshold.unwrap_or(default));
+                Material::Cutout(Arc::new(if stochastic { data.stochastic() } else { data }))
+            }
+            "coated" => Material::Coated(Arc::new(CoatedData::new(
+                self.material_ref(tokens)?,
+                tokens.number("refraction index")?,
+                if tokens.has_more() {
+                    tokens.number("roughness")?
+                } else {
+                    0.0
+                },
+            ))),
+            "conductor" => {
+                let data = match tokens.word("conductor preset")? {
+                    "custom" => ConductorData::new(
+                        tokens.vector("eta")?,
+                        tokens.vector("k")?,
+                        tokens.number("roughness")?,
+                    ),
+                    preset => {
+                        let roughness = tokens.number("roughness")?;
+                        ConductorData::preset(preset, roughness).ok_or_else(|| {
+                            tokens.error(format!("unknown conductor preset '{}'", preset))
+                        })?
+                    }
+                };
+                Material::Conductor(match film(tokens)? {
+                    Some(film) => data.with_film(film),
+                    None => data,
+                })
+            }
+            kind => return Err(tokens.error(format!("unknown material type '{}'", kind))),
+        };
+        self.materials.insert(name.to_string(), material);
+        Ok(())
+    }
+
+    // A previously declared material, by name.
+    fn material_ref(&self, tokens: &mut Tokens) -> Result<Material, SceneFileError> {
+        let name = tokens.word("material name")?;
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| tokens.error(format!("undefined material '{}'", name)))
+    }
+
+    fn light(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let light = match tokens.word("light type")? {
+            "point" => {
+                let light = PointLightData::new(
+                    tokens.vector("light position")?,
+                    tokens.vector("light intensity")?,
+                );
+                Light::Point(match self.profile(tokens)? {
+                    Some((profile, scale)) => PointLightData {
+                        intensity: light.intensity * scale,
+                        ..light
+                    }
+                    .with_profile(profile),
+                    None => light,
+                })
+            }
+            "spot" => {
+                let position = tokens.vector("light position")?;
+                let target = tokens.vector("light target")?;
+                let light = SpotLightData::new(
+                    position,
+                    target - position,
+                    tokens.vector("light intensity")?,
+                    tokens.number::<f64>("cone angle")?.to_radians(),
+                    tokens.number::<f64>("falloff start")?.to_radians(),
+                );
+                Light::Spot(match self.profile(tokens)? {
+                    Some((profile, scale)) => SpotLightData {
+                        intensity: light.intensity * scale,
+                        ..light
+                    }
+                    .with_profile(profile),
+                    None => light,
+                })
+            }
+            "directional" => Light::Directional(DirectionalLightData::new(
+                tokens.vector("light direction")?,
+                tokens.vector("light irradiance")?,
+            )),
+            kind => return Err(tokens.error(format!("unknown light type '{}'", kind))),
+        };
+        self.lighting.add(light);
+        Ok(())
+    }
+
+    // Optional trailing `ies <path> [photometric]` on point and spot lights,
+    // with the factor the light's intensity is scaled by: one, or the
+    // profile's peak candela when it keeps the file's photometry.
+    fn profile(
+        &self,
+        tokens: &mut Tokens,
+    ) -> Result<Option<(Arc<IesProfile>, f64)>, SceneFileError> {
+        if !tokens.has_more() {
+            return Ok(None);
+        }
+        match tokens.word("light key")? {
+            "ies" => {
+                let file = tokens.word("IES profile path")?;
+                let profile = IesProfile::load(&self.directory.join(file)).map_err(|err| {
+                    tokens.error(format!("failed to load IES profile '{}': {}", file, err))
+                })?;
+                let scale = if !tokens.has_more() {
+                    1.0
+                } else {
+                    match tokens.word("IES key")? {
+                        "photometric" => profile.peak(),
+                        key => return Err(tokens.error(format!("unknown IES key '{}'", key))),
+                    }
+                };
+                Ok(Some((Arc::new(profile), scale)))
+            }
+            key => Err(tokens.error(format!("unknown light key '{}'", key))),
+        }
+    }
+
+    fn environment(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let file = tokens.word("environment map path")?;
+        let image = Image::load(&self.directory.join(file))
+            .map_err(|err| tokens.error(format!("failed to load image '{}': {}", file, err)))?;
+        let mut rotation = 0.0;
+        let mut intensity = 1.0;
+        while tokens.has_more() {
+            match tokens.word("environment key")? {
+                "rotation" => rotation = tokens.number::<f64>("rotation")?.to_radians(),
+                "intensity" => intensity = tokens.number("intensity")?,
+                key => return Err(tokens.error(format!("unknown environment key '{}'", key))),
+            }
+        }
+        self.lighting.background = Background::Environment(Arc::new(EnvironmentMap::new(
+            Arc::new(image),
+            rotation,
+            intensity,
+        )));
+        Ok(())
+    }
+
+    fn sky(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let mut elevation = 45.0;
+        let mut azimuth = 0.0;
+        let mut turbidity = 3.0;
+        let mut intensity = 1.0;
+        let mut sun_size = 0.53; // apparent diameter of the real sun
+        while tokens.has_more() {
+            match tokens.word("sky key")? {
+                "elevation" => elevation = tokens.number("sun elevation")?,
+                "azimuth" => azimuth = tokens.number("sun azimuth")?,
+                "turbidity" => turbidity = tokens.number("turbidity")?,
+                "intensity" => intensity = tokens.number("intensity")?,
+                "sun_size" => sun_size = tokens.number("sun size")?,
+                key => return Err(tokens.error(format!("unknown sky key '{}'", key))),
+            }
+        }
+        self.lighting.background = Background::Sky(Arc::new(PhysicalSky::new(
+            f64::to_radians(elevation),
+            f64::to_radians(azimuth),
+            turbidity,
+            intensity,
+            f64::to_radians(sun_size / 2.0),
+        )));
+        Ok(())
+    }
+
+    fn sphere(&mut self, tokens: &mut Tokens) -> Result<(), SceneFileError> {
+        let center = tokens.vector("sphere center")?;
+        let radius = tokens.number("sphere radius")?;
+        let material = self.material_ref(tokens)?;
+        self.world
+            .add(Target::Sphere(SphereData::new(center, radius, material)));
+        Ok(())
+    }
+}
@@ -0,0 +1,183 @@
+use std::f64::consts::PI;
+
+use super::{Onb, Vector, RGB};
+
+const SKY_SCALE: f64 = 0.05; // luminance in kcd/m^2 to scene radiance
+const SUN_IRRADIANCE: f64 = 8.0; // at normal incidence above the atmosphere, in scene units
+
+// Analytic clear sky of Preetham, Shirley and Smits (1999) with a sun disk.
+// Elevation and azimuth place the sun; azimuth zero faces -z and positive
+// angles turn towards +x. Turbidity runs from about 2 (very clear) to 10
+// (hazy). The sun's colour comes from its Rayleigh and aerosol extinction
+// through the air mass at that elevation, and the disk is sampled as a
+// light so small suns stay noise free.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalSky {
+    pub sun_direction: Vector<3>,
+    pub turbidity: f64,
+    pub intensity: f64,
+    pub sun_radius: f64, // angular radius in radians
+    sun_radiance: RGB,
+    zenith: [f64; 3], // Y, x, y at the zenith
+    perez: [[f64; 5]; 3],
+}
+
+impl PhysicalSky {
+    // Angles in radians.
+    pub fn new(
+        elevation: f64,
+        azimuth: f64,
+        turbidity: f64,
+        intensity: f64,
+        sun_radius: f64,
+    ) -> Self {
+        let t = turbidity.clamp(1.7, 10.0);
+        let sun_direction = Vector::new([
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        ]);
+        let theta_s = (PI / 2.0 - elevation).clamp(0.0, PI / 2.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = ((4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192).max(0.0);
+        let chromaticity = |m: [[f64; 4]; 3]| {
+            let angles = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+            let weights = [t * t, t, 1.0];
+            (0..3)
+                .map(|i| weights[i] * (0..4).map(|j| m[i][j] * angles[j]).sum::<f64>())
+                .sum::<f64>()
+        };
+        let x = chromaticity([
+            [0.00166, -0.00375, 0.00209, 0.0],
+            [-0.02903, 0.06377, -0.03202, 0.00394],
+            [0.11693, -0.21196, 0.06052, 0.25886],
+        ]);
+        let y = chromaticity([
+            [0.00275, -0.00610, 0.00317, 0.0],
+            [-0.04214, 0.08970, -0.04153, 0.00516],
+            [0.15346, -0.26756, 0.06670, 0.26688],
+        ]);
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let solid_angle = 2.0 * PI * (1.0 - sun_radius.cos());
+        let sun_radiance = if elevation > 0.0 && solid_angle > 0.0 {
+            sun_transmittance(theta_s, t) * (SUN_IRRADIANCE / solid_angle)
+        } else {
+            RGB::zero()
+        };
+
+        Self {
+            sun_direction,
+            turbidity: t,
+            intensity,
+            sun_radius,
+            sun_radiance,
+            zenith: [luminance, x, y],
+            perez,
+        }
+    }
+
+    pub fn radiance(&self, direction: Vector<3>) -> RGB {
+        let direction = direction.normalize();
+        let mut radiance = self.sky(direction);
+        if direction.dot(self.sun_direction) >= self.sun_radius.cos() {
+            radiance = radiance + self.sun_radiance;
+        }
+        radiance * self.intensity
+    }
+
+    // A direction inside the sun disk, the radiance from there and its
+    // solid angle density.
+    pub fn sample(&self) -> Option<(Vector<3>, RGB, f64)> {
+        if self.sun_radiance == RGB::zero() {
+            return None;
+        }
+        let cos_max = self.sun_radius.cos();
+        let cos_theta = 1.0 - fastrand::f64() * (1.0 - cos_max);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * fastrand::f64();
+        let local = Vector::new([sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta]);
+        let direction = Onb::new(self.sun_direction).to_world(local);
+        Some((direction, self.radiance(direction), self.pdf(direction)))
+    }
+
+    pub fn pdf(&self, direction: Vector<3>) -> f64 {
+        let cos_max = self.sun_radius.cos();
+        if self.sun_radiance == RGB::zero()
+            || direction.normalize().dot(self.sun_direction) < cos_max
+        {
+            return 0.0;
+        }
+        1.0 / (2.0 * PI * (1.0 - cos_max))
+    }
+
+    // Perez distributions of luminance and chromaticity, relative to the
+    // zenith and converted from xyY to linear sRGB.
+    fn sky(&self, direction: Vector<3>) -> RGB {
+        let cos_theta = direction.components[1].max(0.01);
+        let cos_gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta_s = self.sun_direction.components[1].clamp(0.0, 1.0).acos();
+
+        let perez = |[a, b, c, d, e]: [f64; 5], cos_theta: f64, gamma: f64| {
+            (1.0 + a * (b / cos_theta).exp())
+                * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+        };
+        let [yz, xz, yz_chroma] = self.zenith;
+        let value = |i: usize, zenith: f64| {
+            zenith * perez(self.perez[i], cos_theta, gamma) / perez(self.perez[i], 1.0, theta_s)
+        };
+        let luminance = value(0, yz) * SKY_SCALE;
+        let x = value(1, xz);
+        let y = value(2, yz_chroma);
+        if y <= 0.0 {
+            return RGB::zero();
+        }
+
+        let big_x = x / y * luminance;
+        let big_z = (1.0 - x - y) / y * luminance;
+        RGB::new(
+            [
+                3.2404542 * big_x - 1.5371385 * luminance - 0.4985314 * big_z,
+                -0.9692660 * big_x + 1.8760108 * luminance + 0.0415560 * big_z,
+                0.0556434 * big_x - 0.2040259 * luminance + 1.0572252 * big_z,
+            ]
+            .map(|c| c.max(0.0)),
+        )
+    }
+}
+
+// Fraction of sunlight surviving Rayleigh and aerosol scattering along the
+// path through the atmosphere, at wavelengths standing in for each channel.
+fn sun_transmittance(theta_s: f64, turbidity: f64) -> RGB {
+    let air_mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+    let beta = 0.04608 * turbidity - 0.04586;
+    RGB::new([0.61, 0.55, 0.465].map(|lambda: f64| {
+        let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+        let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+        rayleigh * aerosol
+    }))
+}
@@ -0,0 +1,191 @@
+use std::sync::OnceLock;
+
+use super::RGB;
+
+// Spectral rendering support. Surfaces stay RGB; wavelengths only matter
+// where a dispersive dielectric bends each one differently.
+
+pub const LAMBDA_MIN: f64 = 360.0; // nm
+pub const LAMBDA_MAX: f64 = 830.0; // nm
+pub const SPECTRUM_SAMPLES: usize = 4;
+pub const LAMBDA_D: f64 = 587.56; // helium d-line, where glass indices are quoted
+
+// Wavelengths carried by one camera sample. The first is the hero
+// wavelength; the others are offset evenly in sample space so a single
+// sample covers the whole spectrum until a dispersive event terminates
+// them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledWavelengths {
+    pub lambda: [f64; SPECTRUM_SAMPLES],
+    pub pdf: [f64; SPECTRUM_SAMPLES],
+}
+
+impl SampledWavelengths {
+    // Importance samples wavelengths by an approximation of the visual
+    // response, which keeps the film weights in `to_rgb` close together.
+    pub fn sample_visible(u: f64) -> Self {
+        let mut lambda = [0.0; SPECTRUM_SAMPLES];
+        let mut pdf = [0.0; SPECTRUM_SAMPLES];
+        for i in 0..SPECTRUM_SAMPLES {
+            let u = (u + i as f64 / SPECTRUM_SAMPLES as f64).fract();
+            lambda[i] = 538.0 - 138.888889 * (0.85691062 - 1.82750197 * u).atanh();
+            pdf[i] = visible_pdf(lambda[i]);
+        }
+        Self { lambda, pdf }
+    }
+
+    pub fn hero(&self) -> f64 {
+        self.lambda[0]
+    }
+
+    // Drops the secondary wavelengths after the path has started to depend
+    // on the hero wavelength alone.
+    pub fn terminate_secondary(&mut self) {
+        if self.secondary_terminated() {
+            return;
+        }
+        for pdf in &mut self.pdf[1..] {
+            *pdf = 0.0;
+        }
+        self.pdf[0] /= SPECTRUM_SAMPLES as f64;
+    }
+
+    pub fn secondary_terminated(&self) -> bool {
+        self.pdf[1..].iter().all(|&pdf| pdf == 0.0)
+    }
+
+    // Film response to `radiance` arriving along a path that carried these
+    // wavelengths. An undispersed path has the same RGB radiance at every
+    // wavelength, whose expected film response is exactly `radiance`.
+    // Otherwise the radiance is uplifted to a spectrum, evaluated at the
+    // surviving wavelengths and projected back through the CIE matching
+    // functions.
+    pub fn to_rgb(&self, radiance: RGB) -> RGB {
+        if !self.secondary_terminated() {
+            return radiance;
+        }
+        let mut rgb = RGB::zero();
+        for (&lambda, &pdf) in self.lambda.iter().zip(&self.pdf) {
+            if pdf > 0.0 {
+                let value = radiance.dot(uplift_basis(lambda));
+                rgb = rgb + rgb_matching(lambda) * (value / pdf);
+            }
+        }
+        rgb / SPECTRUM_SAMPLES as f64
+    }
+}
+
+fn visible_pdf(lambda: f64) -> f64 {
+    if !(LAMBDA_MIN..=LAMBDA_MAX).contains(&lambda) {
+        return 0.0;
+    }
+    0.0039398042 / (0.0072 * (lambda - 538.0)).cosh().powi(2)
+}
+
+// CIE 1931 colour matching functions, using the multi-lobe Gaussian fit of
+// Wyman, Sloan and Shirley (2013).
+pub fn xyz_matching(lambda: f64) -> [f64; 3] {
+    let g = |mu: f64, sigma_low: f64, sigma_high: f64| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    [
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    ]
+}
+
+// Matching functions expressed in linear sRGB primaries.
+pub fn rgb_matching(lambda: f64) -> RGB {
+    let [x, y, z] = xyz_matching(lambda);
+    RGB::new([
+        3.2404542 * x - 1.5371385 * y - 0.4985314 * z,
+        -0.9692660 * x + 1.8760108 * y + 0.0415560 * z,
+        0.0556434 * x - 0.2040259 * y + 1.0572252 * z,
+    ])
+}
+
+// Spectral basis dual to `rgb_matching`: the spectrum rgb . basis(lambda)
+// projects back onto exactly rgb, so uplifting is unbiased.
+fn uplift_basis(lambda: f64) -> RGB {
+    static GRAM_INVERSE: OnceLock<[RGB; 3]> = OnceLock::new();
+    let inverse = GRAM_INVERSE.get_or_init(|| {
+        let mut gram = [[0.0; 3]; 3];
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as usize;
+        for step in 0..=steps {
+            let m = rgb_matching(LAMBDA_MIN + step as f64).components;
+            for (i, row) in gram.iter_mut().enumerate() {
+                for (j, value) in row.iter_mut().enumerate() {
+                    *value += m[i] * m[j];
+                }
+            }
+        }
+        invert(gram)
+    });
+    let m = rgb_matching(lambda);
+    RGB::new(inverse.map(|row| row.dot(m)))
+}
+
+fn invert(m: [[f64; 3]; 3]) -> [RGB; 3] {
+    let [[a, b, c], [d, e, f], [g, h, i]] = m;
+    let cofactor = [
+        [e * i - f * h, c * h - b * i, b * f - c * e],
+        [f * g - d * i, a * i - c * g, c * d - a * f],
+        [d * h - e * g, b * g - a * h, a * e - b * d],
+    ];
+    let det = a * cofactor[0][0] + b * cofactor[1][0] + c * cofactor[2][0];
+    cofactor.map(|row| RGB::new(row.map(|value| value / det)))
+}
+
+// Wavelength dependent refractive index. Coefficients use wavelengths in
+// micrometres, as in the optical glass catalogues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dispersion {
+    Cauchy { a: f64, b: f64 },
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    pub fn bk7() -> Self {
+        Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    pub fn fused_silica() -> Self {
+        Dispersion::Sellmeier {
+            b: [0.6961663, 0.4079426, 0.8974794],
+            c: [0.0046791, 0.0135121, 97.9340025],
+        }
+    }
+
+    pub fn diamond() -> Self {
+        Dispersion::Sellmeier {
+            b: [0.3306, 4.3356, 0.0],
+            c: [0.030625, 0.011236, 0.0],
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "bk7" => Some(Self::bk7()),
+            "fused_silica" => Some(Self::fused_silica()),
+            "diamond" => Some(Self::diamond()),
+            _ => None,
+        }
+    }
+
+    pub fn refraction_index(&self, lambda: f64) -> f64 {
+        let micrometres = lambda / 1000.0;
+        let l2 = micrometres * micrometres;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f64 = (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
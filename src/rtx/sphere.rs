@@ -1,6 +1,8 @@
-use super::{Hit, HittableObject, Interval, Material, Ray, Vector};
+use std::f64::consts::PI;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+use super::{stats, Hit, HittableObject, Interval, Material, Ray, Vector};
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SphereData {
     pub center: Vector<3>,
     pub radius: f64,
@@ -15,10 +17,27 @@ impl SphereData {
             material,
         }
     }
+
+    fn hit_at(&self, ray: Ray, t: f64) -> Hit<'_> {
+        let p = ray.at(t);
+        let outward_normal = (p - self.center) / self.radius;
+        let (dpdu, dpdv) = sphere_derivatives(outward_normal, self.radius);
+        Hit::new(
+            p,
+            t,
+            sphere_uv(outward_normal),
+            ray,
+            outward_normal,
+            self,
+            &self.material,
+        )
+        .with_derivatives(dpdu, dpdv)
+    }
 }
 
 impl HittableObject for SphereData {
-    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit> {
+    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit<'_>> {
+        stats::record_intersection_test();
         let oc = self.center - ray.origin;
         let a = ray.direction.length_squared();
         let h = ray.direction.dot(oc);
@@ -29,25 +48,34 @@ impl HittableObject for SphereData {
             return None;
         }
 
+        // The far root is still needed when the near one is cut out.
         let sqrtd = disc.sqrt();
-        let mut root = (h - sqrtd) / a;
-        if !t.surrounds(root) {
-            root = (h + sqrtd) / a;
-            if !t.surrounds(root) {
-                return None;
-            }
-        }
+        [(h - sqrtd) / a, (h + sqrtd) / a]
+            .into_iter()
+            .filter(|&root| t.surrounds(root))
+            .map(|root| self.hit_at(ray, root))
+            .find(|hit| self.material.is_opaque(hit))
+    }
+}
 
-        let t = root;
-        let p = ray.at(t);
-        let outward_normal = (p - self.center) / self.radius;
-        Some(Hit::new(
-            p,
-            t,
-            ray,
-            outward_normal,
-            super::Target::Sphere(*self),
-            self.material,
-        ))
+// Longitude and latitude of a point on the unit sphere, both in [0, 1],
+// with v increasing from the -y pole to the +y pole.
+fn sphere_uv(p: Vector<3>) -> (f64, f64) {
+    let [x, y, z] = p.components;
+    let theta = (-y).clamp(-1.0, 1.0).acos();
+    let phi = (-z).atan2(x) + PI;
+    (phi / (2.0 * PI), theta / PI)
+}
+
+// Partial derivatives of the surface point with respect to `sphere_uv`.
+// dpdv vanishes at the poles, where the parameterization is singular.
+fn sphere_derivatives(n: Vector<3>, radius: f64) -> (Vector<3>, Vector<3>) {
+    let [x, y, z] = n.components;
+    let dpdu = (2.0 * PI * radius) * Vector::new([z, 0.0, -x]);
+    let sin_theta = (1.0 - y * y).max(0.0).sqrt();
+    if sin_theta < 1e-8 {
+        return (dpdu, Vector::zero());
     }
+    let dpdv = (PI * radius) * Vector::new([-x * y / sin_theta, sin_theta, -y * z / sin_theta]);
+    (dpdu, dpdv)
 }
@@ -0,0 +1,94 @@
+use std::cell::Cell;
+use std::fmt;
+use std::time::Duration;
+
+// Per thread counters, cheap enough to bump on every ray and every
+// primitive test. Workers drain them into `RenderStats` when they finish.
+thread_local! {
+    static RAYS_TRACED: Cell<u64> = const { Cell::new(0) };
+    static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+pub fn record_ray() {
+    RAYS_TRACED.with(|count| count.set(count.get() + 1));
+}
+
+pub fn record_intersection_test() {
+    INTERSECTION_TESTS.with(|count| count.set(count.get() + 1));
+}
+
+/// Returns (rays traced, intersection tests) on this thread and resets both.
+pub fn take_counters() -> (u64, u64) {
+    (
+        RAYS_TRACED.with(|count| count.replace(0)),
+        INTERSECTION_TESTS.with(|count| count.replace(0)),
+    )
+}
+
+// Phase covering only the tracing itself, which throughput is measured over.
+pub const RENDER_PHASE: &str = "render";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RenderStats {
+    pub rays_traced: u64,
+    pub intersection_tests: u64,
+    pub phases: Vec<(&'static str, Duration)>, // wall time per phase, in order
+}
+
+impl RenderStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_counters(&mut self, (rays_traced, intersection_tests): (u64, u64)) {
+        self.rays_traced += rays_traced;
+        self.intersection_tests += intersection_tests;
+    }
+
+    /// Adds to an existing phase of the same name so repeated phases, such
+    /// as one checkpoint write per pass, are reported as a single total.
+    pub fn add_phase(&mut self, name: &'static str, duration: Duration) {
+        match self.phases.iter_mut().find(|(phase, _)| *phase == name) {
+            Some((_, total)) => *total += duration,
+            None => self.phases.push((name, duration)),
+        }
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+
+    pub fn phase_time(&self, name: &str) -> Duration {
+        self.phases
+            .iter()
+            .find(|(phase, _)| *phase == name)
+            .map_or(Duration::ZERO, |(_, duration)| *duration)
+    }
+
+    /// Over the render phase alone, so scene setup and checkpoint writes do
+    /// not dilute it.
+    pub fn rays_per_second(&self) -> f64 {
+        let seconds = self.phase_time(RENDER_PHASE).as_secs_f64();
+        if seconds > 0.0 {
+            self.rays_traced as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Rays traced:        {}", self.rays_traced)?;
+        writeln!(f, "Intersection tests: {}", self.intersection_tests)?;
+        writeln!(f, "Rays/sec:           {:.0}", self.rays_per_second())?;
+        for (name, duration) in &self.phases {
+            writeln!(f, "  {:<16} {:.3}s", name, duration.as_secs_f64())?;
+        }
+        write!(
+            f,
+            "Total time:         {:.3}s",
+            self.total_time().as_secs_f64()
+        )
+    }
+}
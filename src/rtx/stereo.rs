@@ -0,0 +1,54 @@
+pub const INTEROCULAR: f64 = 0.064; // typical eye separation, for scenes in metres
+
+// Left and right eye views rendered into one image for VR review. The eyes
+// sit `interocular` apart and their views meet at `convergence`, where
+// objects appear at screen depth. With a panoramic projection each
+// direction is seen from a pair of eyes turned towards it, which gives
+// omni-directional stereo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stereo {
+    pub layout: StereoLayout,
+    pub interocular: f64, // distance between the eyes, in scene units
+    pub convergence: f64, // distance from the camera where the views meet
+}
+
+// Where each eye goes in the output. Each eye gets half of the image, so
+// e.g. a side by side equirectangular panorama is rendered at 4:1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StereoLayout {
+    SideBySide, // left eye on the left
+    TopBottom,  // left eye on top
+}
+
+impl StereoLayout {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "side_by_side" | "side-by-side" => Some(StereoLayout::SideBySide),
+            "top_bottom" | "top-bottom" => Some(StereoLayout::TopBottom),
+            _ => None,
+        }
+    }
+}
+
+impl Stereo {
+    pub fn new(layout: StereoLayout, interocular: f64, convergence: f64) -> Self {
+        Self {
+            layout,
+            interocular,
+            convergence,
+        }
+    }
+
+    // Splits a point (s, t) of the whole image, both from 0 at the top left
+    // to 1 at the bottom right, into the eye it belongs to (-1 left, 1
+    // right), the point within that eye's view and by how much the eye's
+    // aspect ratio differs from the image's.
+    pub fn split(&self, s: f64, t: f64) -> (f64, f64, f64, f64) {
+        match self.layout {
+            StereoLayout::SideBySide if s < 0.5 => (-1.0, 2.0 * s, t, 0.5),
+            StereoLayout::SideBySide => (1.0, 2.0 * s - 1.0, t, 0.5),
+            StereoLayout::TopBottom if t < 0.5 => (-1.0, s, 2.0 * t, 2.0),
+            StereoLayout::TopBottom => (1.0, s, 2.0 * t - 1.0, 2.0),
+        }
+    }
+}
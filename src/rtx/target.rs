@@ -1,12 +1,12 @@
 use super::{HittableObject, Interval, Ray, SphereData};
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Target {
     Sphere(SphereData),
 }
 
 impl HittableObject for Target {
-    fn hit(&self, ray: Ray, t: Interval) -> Option<super::Hit> {
+    fn hit(&self, ray: Ray, t: Interval) -> Option<super::Hit<'_>> {
         match self {
             Target::Sphere(data) => data.hit(ray, t),
         }
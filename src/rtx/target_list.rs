@@ -16,7 +16,7 @@ impl TargetList {
 }
 
 impl HittableObject for TargetList {
-    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit> {
+    fn hit(&self, ray: Ray, t: Interval) -> Option<Hit<'_>> {
         let mut closest_hit = None;
         let mut closest_so_far = t.max;
 
@@ -30,3 +30,9 @@ impl HittableObject for TargetList {
         closest_hit
     }
 }
+
+impl Default for TargetList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use super::{Vector, RGB};
+use crate::image::Image;
+
+// A value that may vary over a surface. Colour parameters read all three
+// channels, scalar parameters the channel average.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Texture {
+    Constant(RGB),
+    Checker { even: RGB, odd: RGB, scale: f64 }, // solid checker with cells of size `scale`
+    Image(Arc<Image>),                           // wrapped over the surface (u, v)
+}
+
+impl Texture {
+    pub fn constant(value: f64) -> Self {
+        Texture::Constant(RGB::new([value, value, value]))
+    }
+
+    pub fn value(&self, u: f64, v: f64, p: Vector<3>) -> RGB {
+        match self {
+            Texture::Constant(color) => *color,
+            Texture::Checker { even, odd, scale } => {
+                let cells: i64 = p
+                    .components
+                    .iter()
+                    .map(|c| (c / scale).floor() as i64)
+                    .sum();
+                if cells % 2 == 0 {
+                    *even
+                } else {
+                    *odd
+                }
+            }
+            Texture::Image(image) => {
+                let u = u.rem_euclid(1.0);
+                let v = 1.0 - v.rem_euclid(1.0);
+                let x = (u * image.width as f64) as usize;
+                let y = (v * image.height as f64) as usize;
+                image.pixel(x, y)
+            }
+        }
+    }
+
+    // Mean value over the surface, for estimating how much light an
+    // emissive texture gives off.
+    pub fn average(&self) -> RGB {
+        match self {
+            Texture::Constant(color) => *color,
+            Texture::Checker { even, odd, .. } => 0.5 * (*even + *odd),
+            Texture::Image(image) => {
+                let total = image
+                    .pixels
+                    .iter()
+                    .fold(RGB::zero(), |total, pixel| total + *pixel);
+                total / image.pixels.len().max(1) as f64
+            }
+        }
+    }
+
+    pub fn scalar(&self, u: f64, v: f64, p: Vector<3>) -> f64 {
+        self.value(u, v, p).components.iter().sum::<f64>() / 3.0
+    }
+}
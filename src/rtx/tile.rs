@@ -0,0 +1,141 @@
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub start_x: usize,
+    pub end_x: usize,
+    pub start_y: usize,
+    pub end_y: usize,
+}
+
+impl Tile {
+    pub fn new(start_x: usize, end_x: usize, start_y: usize, end_y: usize) -> Self {
+        Self {
+            start_x,
+            end_x,
+            start_y,
+            end_y,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.end_x - self.start_x
+    }
+
+    pub fn height(&self) -> usize {
+        self.end_y - self.start_y
+    }
+
+    pub fn pixel_count(&self) -> usize {
+        self.width() * self.height()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileOrder {
+    Scanline, // left to right, top to bottom
+    Spiral,   // outwards from the image center
+    Hilbert,  // along a Hilbert curve, keeps consecutive tiles adjacent
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TileOptions {
+    pub tile_size: usize,   // edge length of the square tiles in pixels
+    pub num_threads: usize, // worker threads pulling tiles
+    pub order: TileOrder,   // order in which tiles are handed out
+    pub seed: Option<u64>,  // seeds every tile's RNG stream; random if unset
+}
+
+impl TileOptions {
+    pub fn new(tile_size: usize, num_threads: usize, order: TileOrder) -> Self {
+        Self {
+            tile_size: tile_size.max(1),
+            num_threads: num_threads.max(1),
+            order,
+            seed: None,
+        }
+    }
+}
+
+impl Default for TileOptions {
+    fn default() -> Self {
+        Self::new(16, available_threads(), TileOrder::Scanline)
+    }
+}
+
+pub fn available_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Splits the image into square tiles (clipped at the right and bottom
+/// edges) and sorts them in the requested order.
+pub fn tiles(image_width: usize, image_height: usize, options: &TileOptions) -> Vec<Tile> {
+    let size = options.tile_size.max(1);
+    let columns = image_width.div_ceil(size);
+    let rows = image_height.div_ceil(size);
+
+    let mut grid = Vec::with_capacity(columns * rows);
+    for row in 0..rows {
+        for column in 0..columns {
+            grid.push((column, row));
+        }
+    }
+
+    match options.order {
+        TileOrder::Scanline => {}
+        TileOrder::Spiral => {
+            let center_x = (columns as f64 - 1.0) / 2.0;
+            let center_y = (rows as f64 - 1.0) / 2.0;
+            grid.sort_by(|&a, &b| {
+                spiral_key(a, center_x, center_y)
+                    .partial_cmp(&spiral_key(b, center_x, center_y))
+                    .expect("spiral keys are finite")
+            });
+        }
+        TileOrder::Hilbert => {
+            let n = columns.max(rows).next_power_of_two();
+            grid.sort_by_key(|&(column, row)| hilbert_index(n, column, row));
+        }
+    }
+
+    grid.into_iter()
+        .map(|(column, row)| {
+            Tile::new(
+                column * size,
+                ((column + 1) * size).min(image_width),
+                row * size,
+                ((row + 1) * size).min(image_height),
+            )
+        })
+        .collect()
+}
+
+// Ring around the center first, then angle within the ring.
+fn spiral_key((column, row): (usize, usize), center_x: f64, center_y: f64) -> (f64, f64) {
+    let dx = column as f64 - center_x;
+    let dy = row as f64 - center_y;
+    (dx.abs().max(dy.abs()), dy.atan2(dx))
+}
+
+// Distance along the Hilbert curve filling an n x n grid (n a power of two).
+fn hilbert_index(n: usize, column: usize, row: usize) -> usize {
+    let (mut x, mut y) = (column, row);
+    let mut d = 0;
+    let mut s = n / 2;
+    while s > 0 {
+        let rx = usize::from(x & s > 0);
+        let ry = usize::from(y & s > 0);
+        d += s * s * ((3 * rx) ^ ry);
+        if ry == 0 {
+            if rx == 1 {
+                x = s - 1 - (x & (s - 1));
+                y = s - 1 - (y & (s - 1));
+            }
+            std::mem::swap(&mut x, &mut y);
+        }
+        s /= 2;
+    }
+    d
+}
@@ -0,0 +1,78 @@
+use std::f64::consts::PI;
+
+use super::{Hit, Onb, Ray, ScatterResult, Vector, RGB};
+
+// Cheap stand-in for subsurface scattering in thin sheets such as paper,
+// leaves and lampshades. Reflection uses wrapped diffuse lighting, which
+// keeps receiving light up to acos(-wrap) past the terminator, and
+// `transmittance` adds a diffuse lobe out of the back of the surface.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TranslucentData {
+    pub reflectance: RGB,
+    pub transmittance: RGB,
+    pub wrap: f64, // 0 is plain Lambertian reflection, 1 wraps all the way round
+}
+
+impl TranslucentData {
+    pub fn new(reflectance: RGB, transmittance: RGB, wrap: f64) -> Self {
+        Self {
+            reflectance,
+            transmittance,
+            wrap: wrap.clamp(0.0, 1.0),
+        }
+    }
+
+    // How often `scatter` picks the reflection lobe.
+    fn reflect_probability(&self) -> Option<f64> {
+        let reflect = mean(self.reflectance);
+        let total = reflect + mean(self.transmittance);
+        (total > 0.0).then(|| reflect / total)
+    }
+
+    pub fn scatter(self, incoming: Ray, hit: &Hit) -> Option<ScatterResult> {
+        let onb = Onb::new(hit.normal);
+        let reflect = fastrand::f64() < self.reflect_probability()?;
+        let phi = 2.0 * PI * fastrand::f64();
+        let cos_theta = if reflect {
+            // Inverts the CDF of the wrapped cosine, (cos + wrap)^2 / (1 + wrap)^2.
+            (1.0 + self.wrap) * fastrand::f64().sqrt() - self.wrap
+        } else {
+            -fastrand::f64().sqrt()
+        };
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let local = Vector::new([sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta]);
+        let direction = onb.to_world(local);
+
+        let pdf = self.pdf(incoming, hit, direction);
+        if pdf == 0.0 {
+            return None;
+        }
+        let attenuation = self.eval(incoming, hit, direction) / pdf;
+        Some(ScatterResult::new(
+            incoming,
+            attenuation,
+            Ray::new(hit.p, direction),
+            pdf,
+        ))
+    }
+
+    pub fn eval(self, _incoming: Ray, hit: &Hit, direction: Vector<3>) -> RGB {
+        let cosine = hit.normal.dot(direction.normalize());
+        let wrapped = (cosine + self.wrap).max(0.0) / (1.0 + self.wrap);
+        self.reflectance * (wrapped / (PI * (1.0 + self.wrap)))
+            + self.transmittance * ((-cosine).max(0.0) / PI)
+    }
+
+    pub fn pdf(self, _incoming: Ray, hit: &Hit, direction: Vector<3>) -> f64 {
+        let Some(reflect) = self.reflect_probability() else {
+            return 0.0;
+        };
+        let cosine = hit.normal.dot(direction.normalize());
+        let wrapped = (cosine + self.wrap).max(0.0) / (PI * (1.0 + self.wrap).powi(2));
+        reflect * wrapped + (1.0 - reflect) * (-cosine).max(0.0) / PI
+    }
+}
+
+fn mean(color: RGB) -> f64 {
+    color.components.iter().sum::<f64>() / 3.0
+}
@@ -86,12 +86,31 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         new_ray.color = ray.color * hit_sphere.albedo;
 
     } else if (hit_sphere.material_id == 1u) {
-        // Metal
-        let reflected = reflect(normalize(ray.direction), hit_normal);
-        let scattered_direction = reflected + hit_sphere.fuzz * random_unit_vector(global_id);
-
-        new_ray.direction = normalize(scattered_direction);
-        new_ray.color = ray.color * hit_sphere.albedo;
+        // Metal: GGX reflection with Schlick's Fresnel, matching the CPU
+        // material. `fuzz` is the perceptual roughness.
+        let wo = -normalize(ray.direction);
+        let n = select(-hit_normal, hit_normal, dot(wo, hit_normal) > 0.0);
+        let alpha = hit_sphere.fuzz * hit_sphere.fuzz;
+
+        if (alpha < 1e-3) {
+            new_ray.direction = reflect(-wo, n);
+            new_ray.color = ray.color * schlick(dot(wo, n), hit_sphere.albedo);
+        } else {
+            let wm = sample_ggx_visible_normal(wo, n, alpha, global_id);
+            let wi = reflect(-wo, wm);
+            if (dot(wi, n) <= 0.0) {
+                // Reflected into the surface: the path is absorbed.
+                new_ray.bounces_left = 0u;
+                new_ray.color = vec3<f32>(0.0, 0.0, 0.0);
+            } else {
+                // With visible normal sampling, BSDF * cos / pdf reduces to
+                // F * G / G1(wo).
+                let g = 1.0 / (1.0 + ggx_lambda(wo, n, alpha) + ggx_lambda(wi, n, alpha));
+                let g1 = 1.0 / (1.0 + ggx_lambda(wo, n, alpha));
+                new_ray.direction = wi;
+                new_ray.color = ray.color * schlick(dot(wo, wm), hit_sphere.albedo) * (g / g1);
+            }
+        }
 
     } else if (hit_sphere.material_id == 2u) {
         // Dielectric (glass).  We use a front_face check and Schlick reflectance.
@@ -153,19 +172,70 @@ fn hit(ro: vec3<f32>, rd: vec3<f32>, center: vec3<f32>, r: f32) -> f32 {
 
 // Random direction on unit sphere
 fn random_unit_vector(global_id: vec3<u32>) -> vec3<f32> {
-    let a = random_f32_with_seed(global_id) * 2.0 * 3.14159265359;
-    let z = random_f32_with_seed(global_id) * 2.0 - 1.0;
+    let a = random_f32_with_salt(global_id, 3u) * 2.0 * 3.14159265359;
+    let z = random_f32_with_salt(global_id, 4u) * 2.0 - 1.0;
     let r = sqrt(1.0 - z * z);
     return vec3<f32>(r * cos(a), r * sin(a), z);
 }
 
+// Visible normal of an isotropic GGX distribution seen from `wo` around the
+// normal `n` (Heitz 2018): stretch to the hemisphere configuration, sample
+// a projected disk, then unstretch.
+fn sample_ggx_visible_normal(wo: vec3<f32>, n: vec3<f32>, alpha: f32, global_id: vec3<u32>) -> vec3<f32> {
+    let helper = select(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(0.0, 1.0, 0.0), abs(n.x) > 0.9);
+    let tangent = normalize(cross(helper, n));
+    let bitangent = cross(n, tangent);
+    let local_wo = vec3<f32>(dot(wo, tangent), dot(wo, bitangent), dot(wo, n));
+
+    let wh = normalize(vec3<f32>(alpha * local_wo.x, alpha * local_wo.y, local_wo.z));
+    var t1 = vec3<f32>(1.0, 0.0, 0.0);
+    if (wh.z < 0.99999) {
+        t1 = normalize(cross(vec3<f32>(0.0, 0.0, 1.0), wh));
+    }
+    let t2 = cross(wh, t1);
+
+    let r = sqrt(random_f32_with_salt(global_id, 1u));
+    let phi = 2.0 * 3.14159265359 * random_f32_with_salt(global_id, 2u);
+    let px = r * cos(phi);
+    let h = sqrt(1.0 - px * px);
+    let s = (1.0 + wh.z) / 2.0;
+    let py = (1.0 - s) * h + s * r * sin(phi);
+    let pz = sqrt(max(0.0, 1.0 - px * px - py * py));
+
+    let nh = px * t1 + py * t2 + pz * wh;
+    let wm = normalize(vec3<f32>(alpha * nh.x, alpha * nh.y, max(nh.z, 1e-6)));
+    return normalize(wm.x * tangent + wm.y * bitangent + wm.z * n);
+}
+
+// Smith's auxiliary function for an isotropic GGX distribution.
+fn ggx_lambda(w: vec3<f32>, n: vec3<f32>, alpha: f32) -> f32 {
+    let cos2_theta = max(dot(w, n) * dot(w, n), 1e-8);
+    let tan2_theta = max(1.0 - cos2_theta, 0.0) / cos2_theta;
+    return (sqrt(1.0 + alpha * alpha * tan2_theta) - 1.0) / 2.0;
+}
+
+// Schlick's approximation with a colored reflectance at normal incidence.
+fn schlick(cosine: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0, 1.0, 1.0) - f0) * pow(1.0 - clamp(cosine, 0.0, 1.0), 5.0);
+}
+
 // Pseudo-random function with pass/bounce seeds
 fn random_f32_with_seed(global_id: vec3<u32>) -> f32 {
-    let seed_x = global_id.x ^ (scene.pass_idx * 374761393u) ^ (scene.bounce_idx * 668265263u);
-    let seed_y = global_id.y ^ (scene.pass_idx * 977123579u) ^ (scene.bounce_idx * 265443578u);
+    return random_f32_with_salt(global_id, 0u);
+}
+
+// As above, with `salt` picking one of several independent values for the
+// same ray and bounce. Chained PCG hashes keep neighbouring rays, passes
+// and bounces uncorrelated.
+fn random_f32_with_salt(global_id: vec3<u32>, salt: u32) -> f32 {
+    let hash = pcg_hash(global_id.x ^ pcg_hash(scene.pass_idx ^ pcg_hash(scene.bounce_idx ^ pcg_hash(salt))));
+    return f32(hash >> 8u) / 16777216.0;
+}
 
-    let t = vec2<f32>(f32(seed_x), f32(seed_y));
-    return fract(sin(dot(t, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+fn pcg_hash(value: u32) -> u32 {
+    let state = value * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
 }
 
 // Reflect a vector around a normal